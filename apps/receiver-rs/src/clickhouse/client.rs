@@ -1,11 +1,17 @@
+use std::io::Write;
 use std::time::Duration;
 
+use flate2::Compression;
+use flate2::write::{GzDecoder, GzEncoder};
 use reqwest::Client;
+use reqwest::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
 use serde::Deserialize;
 
-use super::types::{ClickHouseRequest, ClickHouseResponseRow, SearchResultRequest};
+use super::query::PageCursor;
+use super::types::{ClickHousePagedRow, ClickHouseRequest, ClickHouseResponseRow, SearchResultRequest};
 
-/// Maximum response size from ClickHouse queries (10 MB).
+/// Maximum response size from ClickHouse queries (10 MB). Applies to the
+/// decompressed size when the response is gzip-encoded, not the wire size.
 const MAX_RESPONSE_SIZE: usize = 10 * 1024 * 1024;
 
 /// ClickHouse HTTP client for inserting and querying request data.
@@ -16,6 +22,12 @@ pub struct ClickHouseClient {
     user: String,
     password: String,
     database: String,
+    /// Gzip-compress insert bodies at or above `compress_min_body_size`, and
+    /// advertise `Accept-Encoding: gzip` on queries.
+    compress: bool,
+    /// Minimum uncompressed insert-body size, in bytes, before bothering to
+    /// gzip it — tiny batches aren't worth the CPU.
+    compress_min_body_size: usize,
 }
 
 #[derive(Debug, Deserialize)]
@@ -24,7 +36,14 @@ struct ClickHouseJsonResponse {
 }
 
 impl ClickHouseClient {
-    pub fn new(base_url: &str, user: &str, password: &str, database: &str) -> Self {
+    pub fn new(
+        base_url: &str,
+        user: &str,
+        password: &str,
+        database: &str,
+        compress: bool,
+        compress_min_body_size: usize,
+    ) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(10))
             .pool_max_idle_per_host(4)
@@ -37,6 +56,8 @@ impl ClickHouseClient {
             user: user.to_string(),
             password: password.to_string(),
             database: database.to_string(),
+            compress,
+            compress_min_body_size,
         }
     }
 
@@ -47,6 +68,17 @@ impl ClickHouseClient {
             return Ok(());
         }
 
+        let start = std::time::Instant::now();
+        let result = self.insert_requests_inner(requests).await;
+        crate::metrics::record_clickhouse_op("insert", start.elapsed());
+        match &result {
+            Ok(()) => crate::metrics::record_clickhouse_rows_inserted(requests.len()),
+            Err(e) => crate::metrics::record_clickhouse_error(clickhouse_error_kind(e)),
+        }
+        result
+    }
+
+    async fn insert_requests_inner(&self, requests: &[ClickHouseRequest]) -> Result<(), String> {
         let query = format!(
             "INSERT INTO `{}`.`requests` FORMAT JSONEachRow",
             escape_clickhouse_identifier(&self.database)
@@ -60,17 +92,22 @@ impl ClickHouseClient {
             body.push('\n');
         }
 
-        let resp = self
+        let request = self
             .client
             .post(&self.base_url)
             .query(&[("query", &query)])
             .header("X-ClickHouse-User", &self.user)
             .header("X-ClickHouse-Key", &self.password)
-            .header("Content-Type", "application/json")
-            .body(body)
-            .send()
-            .await
-            .map_err(|e| format!("network: {e}"))?;
+            .header("Content-Type", "application/json");
+
+        let request = if self.compress && body.len() >= self.compress_min_body_size {
+            let compressed = gzip_compress(body.as_bytes()).map_err(|e| format!("compress: {e}"))?;
+            request.header(CONTENT_ENCODING, "gzip").body(compressed)
+        } else {
+            request.body(body)
+        };
+
+        let resp = request.send().await.map_err(|e| format!("network: {e}"))?;
 
         if !resp.status().is_success() {
             let status = resp.status();
@@ -83,13 +120,28 @@ impl ClickHouseClient {
 
     /// Query requests from ClickHouse. Returns parsed search results.
     pub async fn query_requests(&self, sql: &str) -> Result<Vec<SearchResultRequest>, String> {
-        let resp = self
+        let start = std::time::Instant::now();
+        let result = self.query_requests_inner(sql).await;
+        crate::metrics::record_clickhouse_op("query", start.elapsed());
+        if let Err(e) = &result {
+            crate::metrics::record_clickhouse_error(clickhouse_error_kind(e));
+        }
+        result
+    }
+
+    async fn query_requests_inner(&self, sql: &str) -> Result<Vec<SearchResultRequest>, String> {
+        let mut request = self
             .client
             .post(&self.base_url)
             .query(&[("default_format", "JSON")])
             .header("X-ClickHouse-User", &self.user)
             .header("X-ClickHouse-Key", &self.password)
-            .header("Content-Type", "text/plain")
+            .header("Content-Type", "text/plain");
+        if self.compress {
+            request = request.header(ACCEPT_ENCODING, "gzip");
+        }
+
+        let resp = request
             .body(sql.to_string())
             .send()
             .await
@@ -101,27 +153,7 @@ impl ClickHouseClient {
             return Err(format!("ClickHouse query failed ({status}): {text}"));
         }
 
-        // Reject oversized responses early via Content-Length before buffering
-        if let Some(cl) = resp.content_length()
-            && cl > MAX_RESPONSE_SIZE as u64
-        {
-            return Err(format!(
-                "ClickHouse response too large: Content-Length {cl} bytes (max {MAX_RESPONSE_SIZE})"
-            ));
-        }
-
-        let body_bytes = resp
-            .bytes()
-            .await
-            .map_err(|e| format!("read response: {e}"))?;
-
-        if body_bytes.len() > MAX_RESPONSE_SIZE {
-            return Err(format!(
-                "ClickHouse response too large: {} bytes (max {})",
-                body_bytes.len(),
-                MAX_RESPONSE_SIZE
-            ));
-        }
+        let body_bytes = read_size_limited_body(resp).await?;
 
         let json_resp: ClickHouseJsonResponse =
             serde_json::from_slice(&body_bytes).map_err(|e| format!("parse response: {e}"))?;
@@ -133,6 +165,135 @@ impl ClickHouseClient {
             .collect())
     }
 
+    /// Execute a `build_request_page_sql`-built keyset-paginated query. `sql`
+    /// must request `limit + 1` rows (as `build_request_page_sql` does) — the
+    /// `limit + 1`th row, if present, is used to compute a next cursor and
+    /// then trimmed, so the returned page never exceeds `limit` rows and
+    /// every response stays well under `MAX_RESPONSE_SIZE` regardless of how
+    /// large the slug's full history is.
+    pub async fn query_page(
+        &self,
+        sql: &str,
+        limit: usize,
+    ) -> Result<(Vec<SearchResultRequest>, Option<String>), String> {
+        let start = std::time::Instant::now();
+        let result = self.query_page_inner(sql, limit).await;
+        crate::metrics::record_clickhouse_op("query_page", start.elapsed());
+        if let Err(e) = &result {
+            crate::metrics::record_clickhouse_error(clickhouse_error_kind(e));
+        }
+        result
+    }
+
+    async fn query_page_inner(
+        &self,
+        sql: &str,
+        limit: usize,
+    ) -> Result<(Vec<SearchResultRequest>, Option<String>), String> {
+        let mut request = self
+            .client
+            .post(&self.base_url)
+            .query(&[("default_format", "JSON")])
+            .header("X-ClickHouse-User", &self.user)
+            .header("X-ClickHouse-Key", &self.password)
+            .header("Content-Type", "text/plain");
+        if self.compress {
+            request = request.header(ACCEPT_ENCODING, "gzip");
+        }
+
+        let resp = request
+            .body(sql.to_string())
+            .send()
+            .await
+            .map_err(|e| format!("network: {e}"))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(format!("ClickHouse page query failed ({status}): {text}"));
+        }
+
+        let body_bytes = read_size_limited_body(resp).await?;
+
+        #[derive(Deserialize)]
+        struct PagedResponse {
+            data: Vec<ClickHousePagedRow>,
+        }
+        let PagedResponse { mut data } =
+            serde_json::from_slice(&body_bytes).map_err(|e| format!("parse response: {e}"))?;
+
+        let has_more = data.len() > limit;
+        if has_more {
+            data.truncate(limit);
+        }
+
+        let next_cursor = if has_more {
+            data.last().and_then(|last| {
+                let row_hash: u64 = last.row_hash.parse().ok()?;
+                Some(
+                    PageCursor {
+                        received_at_ms: crate::time::parse_received_at(&last.row.received_at) as i64,
+                        row_hash,
+                    }
+                    .encode(),
+                )
+            })
+        } else {
+            None
+        };
+
+        let rows = data.iter().map(|p| SearchResultRequest::from_row(&p.row)).collect();
+        Ok((rows, next_cursor))
+    }
+
+    /// Run a `count()` query and return the single scalar result. Used by the
+    /// search endpoint's `count` mode — same transport as `query_requests`
+    /// but decodes `{"data": [{"count": "N"}]}` instead of full rows.
+    pub async fn query_count(&self, sql: &str) -> Result<u64, String> {
+        #[derive(Deserialize)]
+        struct CountRow {
+            count: String,
+        }
+        #[derive(Deserialize)]
+        struct CountResponse {
+            data: Vec<CountRow>,
+        }
+
+        let mut request = self
+            .client
+            .post(&self.base_url)
+            .query(&[("default_format", "JSON")])
+            .header("X-ClickHouse-User", &self.user)
+            .header("X-ClickHouse-Key", &self.password)
+            .header("Content-Type", "text/plain");
+        if self.compress {
+            request = request.header(ACCEPT_ENCODING, "gzip");
+        }
+
+        let resp = request
+            .body(sql.to_string())
+            .send()
+            .await
+            .map_err(|e| format!("network: {e}"))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(format!("ClickHouse count query failed ({status}): {text}"));
+        }
+
+        let body_bytes = read_size_limited_body(resp).await?;
+
+        let parsed: CountResponse =
+            serde_json::from_slice(&body_bytes).map_err(|e| format!("parse response: {e}"))?;
+
+        parsed
+            .data
+            .first()
+            .map(|row| row.count.parse().unwrap_or(0))
+            .ok_or_else(|| "ClickHouse count query returned no rows".to_string())
+    }
+
     /// Delete requests older than `retention_days` for the given user IDs.
     /// Executes a ClickHouse mutation (`ALTER TABLE ... DELETE WHERE ...`).
     pub async fn delete_old_requests_for_users(
@@ -164,6 +325,69 @@ impl ClickHouseClient {
         Ok(())
     }
 
+    /// The configured database name, for callers (e.g. `storage::StorageBackend`)
+    /// that need to build SQL outside this module.
+    pub(crate) fn database(&self) -> &str {
+        &self.database
+    }
+
+    /// Generic `GROUP BY`/aggregate query — returns whatever row shape the
+    /// caller's `T` describes instead of the fixed `SearchResultRequest` shape
+    /// `query_requests` assumes. Used by `handlers::facets` for per-column
+    /// counts and the time-bucketed histogram.
+    pub async fn query_json<T>(&self, sql: &str) -> Result<Vec<T>, String>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let start = std::time::Instant::now();
+        let result = self.query_json_inner(sql).await;
+        crate::metrics::record_clickhouse_op("query_json", start.elapsed());
+        if let Err(e) = &result {
+            crate::metrics::record_clickhouse_error(clickhouse_error_kind(e));
+        }
+        result
+    }
+
+    async fn query_json_inner<T>(&self, sql: &str) -> Result<Vec<T>, String>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        #[derive(Deserialize)]
+        struct JsonResponse<T> {
+            data: Vec<T>,
+        }
+
+        let mut request = self
+            .client
+            .post(&self.base_url)
+            .query(&[("default_format", "JSON")])
+            .header("X-ClickHouse-User", &self.user)
+            .header("X-ClickHouse-Key", &self.password)
+            .header("Content-Type", "text/plain");
+        if self.compress {
+            request = request.header(ACCEPT_ENCODING, "gzip");
+        }
+
+        let resp = request
+            .body(sql.to_string())
+            .send()
+            .await
+            .map_err(|e| format!("network: {e}"))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(format!("ClickHouse JSON query failed ({status}): {text}"));
+        }
+
+        let body_bytes = read_size_limited_body(resp).await?;
+
+        let parsed: JsonResponse<T> =
+            serde_json::from_slice(&body_bytes).map_err(|e| format!("parse response: {e}"))?;
+
+        Ok(parsed.data)
+    }
+
     /// Check if ClickHouse is reachable (simple ping).
     pub async fn ping(&self) -> bool {
         self.client
@@ -175,6 +399,87 @@ impl ClickHouseClient {
     }
 }
 
+/// Classify one of `ClickHouseClient`'s `Result<_, String>` error messages
+/// into a Prometheus label. The error strings are all constructed locally
+/// (see `insert_requests_inner`/`query_requests_inner`) with a stable prefix
+/// per failure site, so matching on that prefix is simpler than threading a
+/// typed error enum through a client that otherwise has none.
+fn clickhouse_error_kind(err: &str) -> &'static str {
+    if err.starts_with("network:") {
+        "network"
+    } else if err.starts_with("serialize:") {
+        "serialize"
+    } else if err.starts_with("compress:") || err.starts_with("decompress:") {
+        "compression"
+    } else {
+        "http_status"
+    }
+}
+
+/// Gzip-compress `data` at the default compression level.
+fn gzip_compress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(data.len() / 4), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Read a ClickHouse response body with size limiting, transparently
+/// decompressing gzip-encoded bodies chunk-by-chunk so a small compressed
+/// payload can't expand into an unbounded allocation before we notice: the
+/// `MAX_RESPONSE_SIZE` check runs against the decompressed size after every
+/// chunk, not just once at the end.
+async fn read_size_limited_body(mut resp: reqwest::Response) -> Result<Vec<u8>, String> {
+    let is_gzip = resp
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("gzip"));
+
+    if !is_gzip {
+        if let Some(cl) = resp.content_length()
+            && cl > MAX_RESPONSE_SIZE as u64
+        {
+            return Err(format!(
+                "ClickHouse response too large: Content-Length {cl} bytes (max {MAX_RESPONSE_SIZE})"
+            ));
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| format!("read response: {e}"))?
+        {
+            body.extend_from_slice(&chunk);
+            if body.len() > MAX_RESPONSE_SIZE {
+                return Err(format!(
+                    "ClickHouse response too large: {} bytes (max {})",
+                    body.len(),
+                    MAX_RESPONSE_SIZE
+                ));
+            }
+        }
+        return Ok(body);
+    }
+
+    let mut decoder = GzDecoder::new(Vec::new());
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .map_err(|e| format!("read response: {e}"))?
+    {
+        decoder
+            .write_all(&chunk)
+            .map_err(|e| format!("decompress: {e}"))?;
+        if decoder.get_ref().len() > MAX_RESPONSE_SIZE {
+            return Err(format!(
+                "ClickHouse response too large: decompressed body exceeds {MAX_RESPONSE_SIZE} bytes"
+            ));
+        }
+    }
+    decoder.finish().map_err(|e| format!("decompress: {e}"))
+}
+
 pub(crate) fn escape_clickhouse_string(input: &str) -> String {
     input.replace('\\', "\\\\").replace('\'', "\\'")
 }
@@ -206,7 +511,7 @@ fn build_delete_sql(database: &str, user_ids: &[String], retention_days: u32) ->
 
 #[cfg(test)]
 mod tests {
-    use super::build_delete_sql;
+    use super::{build_delete_sql, gzip_compress};
 
     #[test]
     fn build_delete_sql_returns_none_for_empty_user_list() {
@@ -229,4 +534,17 @@ mod tests {
         let sql = build_delete_sql("web`hooks", &["user_1".to_string()], 7).expect("expected SQL");
         assert!(sql.contains("ALTER TABLE `web``hooks`.`requests` DELETE"));
     }
+
+    #[test]
+    fn gzip_compress_round_trips_via_flate2_decoder() {
+        let body = "{\"a\":1}\n{\"a\":2}\n".repeat(64);
+        let compressed = gzip_compress(body.as_bytes()).expect("gzip compress");
+
+        assert!(compressed.len() < body.len());
+
+        let mut decoder = flate2::read::GzDecoder::new(&compressed[..]);
+        let mut decompressed = String::new();
+        std::io::Read::read_to_string(&mut decoder, &mut decompressed).expect("gunzip");
+        assert_eq!(decompressed, body);
+    }
 }
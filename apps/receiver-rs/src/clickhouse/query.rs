@@ -0,0 +1,381 @@
+use super::client::{escape_clickhouse_identifier, escape_clickhouse_string};
+use crate::handlers::webhook::is_valid_slug;
+use crate::time::{epoch_ms_to_ch_decimal, parse_rfc3339_ms};
+
+/// ClickHouse has no stored row id, so keyset pagination (`build_request_page_sql`)
+/// needs a deterministic per-row tie-breaker for rows that share a `received_at`
+/// millisecond. `cityHash64` over the same distinguishing fields
+/// `SearchResultRequest::from_row` hashes for its display `id` gives one,
+/// computed server-side so the exact same value drives both `ORDER BY` and
+/// the `WHERE` cutoff.
+pub(crate) const ROW_HASH_EXPR: &str = "cityHash64(method, path, headers, body, query_params, ip)";
+
+/// Query parameters accepted by `GET /endpoints/:slug/requests/search`.
+/// All fields are plain strings straight off the query string — parsing and
+/// validation happen in `build_request_search_sql`/`build_request_count_sql`
+/// so both paths share identical rejection behavior.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct RequestSearchParams {
+    /// Epoch-ms integer or ISO-8601 string, inclusive lower bound on `received_at`.
+    pub from: Option<String>,
+    /// Epoch-ms integer or ISO-8601 string, inclusive upper bound on `received_at`.
+    pub to: Option<String>,
+    /// Comma-separated list; `=` for one value, `IN (...)` for several.
+    pub method: Option<String>,
+    /// Comma-separated list; `=` for one value, `IN (...)` for several.
+    pub content_type: Option<String>,
+    /// Comma-separated list; `=` for one value, `IN (...)` for several.
+    pub ip: Option<String>,
+    /// Substring match against `path` (also matches as a prefix).
+    pub path: Option<String>,
+    /// Token search against the request body (`hasToken` + `ILIKE` fallback).
+    pub q: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    /// Opt into keyset pagination (`build_request_page_sql`/`query_page`)
+    /// instead of the default `offset`-based paging: omit this field
+    /// entirely for the old behavior; pass it empty for a first page, or
+    /// with the previous response's `nextCursor` to continue one.
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestSearchError {
+    InvalidSlug,
+    InvalidTimeRange,
+    InvalidCursor,
+}
+
+impl std::fmt::Display for RequestSearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestSearchError::InvalidSlug => write!(f, "invalid slug"),
+            RequestSearchError::InvalidTimeRange => write!(f, "invalid from/to timestamp"),
+            RequestSearchError::InvalidCursor => write!(f, "invalid cursor"),
+        }
+    }
+}
+
+/// Opaque keyset-pagination cursor for `build_request_page_sql`/
+/// `ClickHouseClient::query_page`. Encodes the `(received_at, row_hash)`
+/// tuple of the last row returned on the previous page, so the next page can
+/// resume with `WHERE (received_at, row_hash) < (cursor...)` instead of an
+/// `OFFSET` that re-scans everything before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageCursor {
+    pub received_at_ms: i64,
+    pub row_hash: u64,
+}
+
+impl PageCursor {
+    pub fn encode(&self) -> String {
+        format!("{}:{:016x}", self.received_at_ms, self.row_hash)
+    }
+
+    /// Parses strictly (`i64`/hex `u64`) so a tampered or garbage cursor is
+    /// rejected rather than silently coerced into SQL.
+    pub fn decode(raw: &str) -> Option<Self> {
+        let (ts, hash) = raw.rsplit_once(':')?;
+        Some(Self {
+            received_at_ms: ts.parse().ok()?,
+            row_hash: u64::from_str_radix(hash, 16).ok()?,
+        })
+    }
+}
+
+/// Parse a `from`/`to` query value as either an epoch-ms integer or an
+/// RFC3339/ISO-8601 datetime string, returning epoch ms.
+fn parse_time_param(raw: &str) -> Option<i64> {
+    if let Ok(ms) = raw.parse::<i64>() {
+        return Some(ms);
+    }
+    parse_rfc3339_ms(raw)
+}
+
+/// Build an `IN`/`=` condition over a comma-separated list of values.
+/// Empty entries (e.g. a trailing comma) are dropped; an all-empty list
+/// produces no condition at all.
+fn in_condition(column: &str, csv: &str) -> Option<String> {
+    let values: Vec<String> = csv
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| format!("'{}'", escape_clickhouse_string(v)))
+        .collect();
+
+    match values.len() {
+        0 => None,
+        1 => Some(format!("{column} = {}", values[0])),
+        _ => Some(format!("{column} IN ({})", values.join(", "))),
+    }
+}
+
+fn time_condition(column: &str, op: &str, raw: &str) -> Result<String, RequestSearchError> {
+    let ms = parse_time_param(raw).ok_or(RequestSearchError::InvalidTimeRange)?;
+    let decimal = epoch_ms_to_ch_decimal(ms);
+    Ok(format!("{column} {op} toDateTime64('{decimal}', 3, 'UTC')"))
+}
+
+/// Shared WHERE-clause builder for both the row query and the count query —
+/// keeping it in one place guarantees `count` and `limit`/`offset` pages see
+/// the exact same filtered set.
+fn build_where_clause(
+    slug: &str,
+    params: &RequestSearchParams,
+) -> Result<String, RequestSearchError> {
+    if !is_valid_slug(slug) {
+        return Err(RequestSearchError::InvalidSlug);
+    }
+
+    let mut conditions = vec![format!("slug = '{}'", escape_clickhouse_string(slug))];
+
+    if let Some(from) = &params.from {
+        conditions.push(time_condition("received_at", ">=", from)?);
+    }
+    if let Some(to) = &params.to {
+        conditions.push(time_condition("received_at", "<=", to)?);
+    }
+
+    if let Some(method) = &params.method
+        && let Some(cond) = in_condition("method", method)
+    {
+        conditions.push(cond);
+    }
+    if let Some(content_type) = &params.content_type
+        && let Some(cond) = in_condition("content_type", content_type)
+    {
+        conditions.push(cond);
+    }
+    if let Some(ip) = &params.ip
+        && let Some(cond) = in_condition("ip", ip)
+    {
+        conditions.push(cond);
+    }
+
+    if let Some(path) = &params.path
+        && !path.is_empty()
+    {
+        let escaped = escape_clickhouse_string(path);
+        conditions.push(format!("positionCaseInsensitive(path, '{escaped}') > 0"));
+    }
+
+    if let Some(q) = &params.q
+        && !q.is_empty()
+    {
+        let escaped = escape_clickhouse_string(q);
+        conditions.push(format!(
+            "(hasToken(lower(body), lower('{escaped}')) OR body ILIKE '%{escaped}%')"
+        ));
+    }
+
+    Ok(conditions.join(" AND "))
+}
+
+/// Build the paginated row query for `GET /endpoints/:slug/requests/search`.
+/// Ordered by `received_at DESC` — `SearchResultRequest::from_row` derives a
+/// stable per-row `id` from the row's content, which callers can use as a cursor.
+pub fn build_request_search_sql(
+    slug: &str,
+    db: &str,
+    params: &RequestSearchParams,
+) -> Result<String, RequestSearchError> {
+    let limit = params.limit.unwrap_or(50).min(200);
+    let offset = params.offset.unwrap_or(0).min(10_000);
+    let where_clause = build_where_clause(slug, params)?;
+    let db = escape_clickhouse_identifier(db);
+
+    Ok(format!(
+        "SELECT endpoint_id, slug, user_id, method, path, headers, body, query_params, ip, content_type, size, is_ephemeral, received_at \
+         FROM `{db}`.`requests` \
+         WHERE {where_clause} \
+         ORDER BY received_at DESC \
+         LIMIT {limit} OFFSET {offset}"
+    ))
+}
+
+/// Build a keyset-paginated row query: same filters as `build_request_search_sql`,
+/// but ordered by `(received_at, row_hash)` and cut off with `< cursor`
+/// instead of `OFFSET`, so a slug with a large history can be read
+/// incrementally without ClickHouse re-scanning every row before the page.
+/// Requests `limit + 1` rows — the caller (`ClickHouseClient::query_page`)
+/// uses the extra row to decide whether a next cursor exists, then trims it
+/// off before returning.
+pub fn build_request_page_sql(
+    slug: &str,
+    db: &str,
+    params: &RequestSearchParams,
+    cursor: Option<PageCursor>,
+    limit: u32,
+) -> Result<String, RequestSearchError> {
+    let limit = limit.clamp(1, 200);
+    let mut where_clause = build_where_clause(slug, params)?;
+
+    if let Some(cursor) = cursor {
+        let decimal = epoch_ms_to_ch_decimal(cursor.received_at_ms);
+        where_clause.push_str(&format!(
+            " AND (received_at, {ROW_HASH_EXPR}) < (toDateTime64('{decimal}', 3, 'UTC'), {})",
+            cursor.row_hash
+        ));
+    }
+
+    let db = escape_clickhouse_identifier(db);
+
+    Ok(format!(
+        "SELECT endpoint_id, slug, user_id, method, path, headers, body, query_params, ip, content_type, size, is_ephemeral, received_at, {ROW_HASH_EXPR} AS row_hash \
+         FROM `{db}`.`requests` \
+         WHERE {where_clause} \
+         ORDER BY received_at DESC, row_hash DESC \
+         LIMIT {}",
+        limit + 1
+    ))
+}
+
+/// Build the `count` mode query: same filters, no pagination, just the total.
+pub fn build_request_count_sql(
+    slug: &str,
+    db: &str,
+    params: &RequestSearchParams,
+) -> Result<String, RequestSearchError> {
+    let where_clause = build_where_clause(slug, params)?;
+    let db = escape_clickhouse_identifier(db);
+
+    Ok(format!(
+        "SELECT count() AS count FROM `{db}`.`requests` WHERE {where_clause}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> RequestSearchParams {
+        RequestSearchParams::default()
+    }
+
+    #[test]
+    fn build_request_search_sql_rejects_invalid_slug() {
+        let err = build_request_search_sql("../bad", "webhooks", &params())
+            .expect_err("invalid slug should fail");
+        assert_eq!(err, RequestSearchError::InvalidSlug);
+    }
+
+    #[test]
+    fn build_request_search_sql_includes_slug_and_defaults() {
+        let sql = build_request_search_sql("demo_slug", "webhooks", &params())
+            .expect("sql should build");
+        assert!(sql.contains("FROM `webhooks`.`requests`"));
+        assert!(sql.contains("slug = 'demo_slug'"));
+        assert!(sql.contains("ORDER BY received_at DESC"));
+        assert!(sql.contains("LIMIT 50 OFFSET 0"));
+    }
+
+    #[test]
+    fn build_request_search_sql_accepts_epoch_ms_and_iso_time_range() {
+        let mut p = params();
+        p.from = Some("1739800496789".to_string());
+        p.to = Some("2026-02-17T13:00:00.000Z".to_string());
+        let sql = build_request_search_sql("demo_slug", "webhooks", &p).expect("sql should build");
+        assert!(sql.contains("received_at >= toDateTime64('1739800496.789', 3, 'UTC')"));
+        assert!(sql.contains("received_at <= toDateTime64("));
+    }
+
+    #[test]
+    fn build_request_search_sql_rejects_unparseable_time() {
+        let mut p = params();
+        p.from = Some("not-a-time".to_string());
+        let err = build_request_search_sql("demo_slug", "webhooks", &p)
+            .expect_err("garbage timestamp should fail");
+        assert_eq!(err, RequestSearchError::InvalidTimeRange);
+    }
+
+    #[test]
+    fn build_request_search_sql_builds_in_clause_for_multiple_methods() {
+        let mut p = params();
+        p.method = Some("GET, POST".to_string());
+        let sql = build_request_search_sql("demo_slug", "webhooks", &p).expect("sql should build");
+        assert!(sql.contains("method IN ('GET', 'POST')"));
+    }
+
+    #[test]
+    fn build_request_search_sql_uses_equality_for_single_value_lists() {
+        let mut p = params();
+        p.ip = Some("127.0.0.1".to_string());
+        let sql = build_request_search_sql("demo_slug", "webhooks", &p).expect("sql should build");
+        assert!(sql.contains("ip = '127.0.0.1'"));
+    }
+
+    #[test]
+    fn build_request_search_sql_includes_path_and_body_token_search() {
+        let mut p = params();
+        p.path = Some("/users".to_string());
+        p.q = Some("order_id".to_string());
+        let sql = build_request_search_sql("demo_slug", "webhooks", &p).expect("sql should build");
+        assert!(sql.contains("positionCaseInsensitive(path, '/users') > 0"));
+        assert!(sql.contains("hasToken(lower(body), lower('order_id'))"));
+        assert!(sql.contains("body ILIKE '%order_id%'"));
+    }
+
+    #[test]
+    fn build_request_search_sql_escapes_injection_attempts() {
+        let mut p = params();
+        p.q = Some("'; DROP TABLE requests--".to_string());
+        let sql = build_request_search_sql("demo_slug", "webhooks", &p).expect("sql should build");
+        assert!(sql.contains("\\'; DROP TABLE requests--"));
+    }
+
+    #[test]
+    fn build_request_count_sql_has_no_pagination() {
+        let sql = build_request_count_sql("demo_slug", "webhooks", &params())
+            .expect("sql should build");
+        assert!(sql.starts_with("SELECT count() AS count"));
+        assert!(!sql.contains("LIMIT"));
+        assert!(!sql.contains("ORDER BY"));
+    }
+
+    #[test]
+    fn page_cursor_round_trips_through_encode_decode() {
+        let cursor = PageCursor {
+            received_at_ms: 1_739_800_496_789,
+            row_hash: 0xdead_beef_cafe_1234,
+        };
+        let decoded = PageCursor::decode(&cursor.encode()).expect("cursor should decode");
+        assert_eq!(decoded, cursor);
+    }
+
+    #[test]
+    fn page_cursor_rejects_garbage() {
+        assert!(PageCursor::decode("not-a-cursor").is_none());
+        assert!(PageCursor::decode("123").is_none());
+        assert!(PageCursor::decode("abc:123").is_none());
+    }
+
+    #[test]
+    fn build_request_page_sql_requests_one_extra_row_and_orders_by_row_hash() {
+        let sql = build_request_page_sql("demo_slug", "webhooks", &params(), None, 50)
+            .expect("sql should build");
+        assert!(sql.contains("cityHash64(method, path, headers, body, query_params, ip) AS row_hash"));
+        assert!(sql.contains("ORDER BY received_at DESC, row_hash DESC"));
+        assert!(sql.contains("LIMIT 51"));
+        assert!(!sql.contains("OFFSET"));
+    }
+
+    #[test]
+    fn build_request_page_sql_applies_cursor_as_keyset_cutoff() {
+        let cursor = PageCursor {
+            received_at_ms: 1_739_800_496_789,
+            row_hash: 42,
+        };
+        let sql = build_request_page_sql("demo_slug", "webhooks", &params(), Some(cursor), 50)
+            .expect("sql should build");
+        assert!(sql.contains(
+            "AND (received_at, cityHash64(method, path, headers, body, query_params, ip)) < (toDateTime64('1739800496.789', 3, 'UTC'), 42)"
+        ));
+    }
+
+    #[test]
+    fn build_request_page_sql_clamps_limit() {
+        let sql = build_request_page_sql("demo_slug", "webhooks", &params(), None, 10_000)
+            .expect("sql should build");
+        assert!(sql.contains("LIMIT 201"));
+    }
+}
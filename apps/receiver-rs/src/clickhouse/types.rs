@@ -5,6 +5,7 @@ use sha2::{Digest, Sha256};
 use serde::{Deserialize, Serialize};
 
 use crate::convex::types::{BufferedRequest, EndpointInfo};
+use crate::time::{epoch_ms_to_ch_decimal, parse_received_at};
 
 /// A request row for ClickHouse insertion.
 #[derive(Debug, Clone, Serialize)]
@@ -60,13 +61,14 @@ impl ClickHouseRequest {
     }
 }
 
-/// Convert epoch milliseconds to a ClickHouse DateTime64(3) compatible string.
-/// ClickHouse accepts epoch seconds as a float (e.g. "1739800496.789").
-/// Uses div_euclid/rem_euclid for correct handling of negative timestamps.
-fn epoch_ms_to_ch_decimal(ms: i64) -> String {
-    let secs = ms.div_euclid(1000);
-    let subsec_ms = ms.rem_euclid(1000) as u64;
-    format!("{secs}.{subsec_ms:03}")
+/// A request row returned from `ClickHouseClient::query_page`, which asks
+/// `build_request_page_sql` to project the keyset tie-breaker alongside the
+/// usual columns (see `clickhouse::query::PageCursor`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClickHousePagedRow {
+    #[serde(flatten)]
+    pub row: ClickHouseResponseRow,
+    pub row_hash: String,
 }
 
 /// A request row returned from ClickHouse queries.
@@ -159,97 +161,18 @@ impl SearchResultRequest {
             received_at,
         }
     }
-}
-
-/// Parse ClickHouse DateTime64 response to epoch milliseconds.
-/// ClickHouse returns DateTime64(3) as "2026-02-17 12:34:56.789" in JSON format.
-fn parse_received_at(s: &str) -> f64 {
-    // Try parsing as epoch seconds with millis (e.g. "1739800496.789")
-    // Sanity check: epoch seconds should be in a reasonable range (2000-01-01 to 2100-01-01)
-    if let Ok(f) = s.parse::<f64>()
-        && f > 946_684_800.0
-        && f < 4_102_444_800.0
-    {
-        return f * 1000.0;
-    }
-    // Try parsing as "YYYY-MM-DD HH:MM:SS.mmm" format
-    // Simple manual parse for the common ClickHouse format
-    if s.len() >= 19 {
-        // We have at least "YYYY-MM-DD HH:MM:SS"
-        let parts: Vec<&str> = s.split('.').collect();
-        let datetime_part = parts[0];
-        let millis: u64 = if parts.len() > 1 {
-            let frac = &parts[1][..parts[1].len().min(3)];
-            // Right-pad with zeros: "7" → 700, "78" → 780, "789" → 789
-            match frac.len() {
-                1 => frac.parse::<u64>().unwrap_or(0) * 100,
-                2 => frac.parse::<u64>().unwrap_or(0) * 10,
-                _ => frac.parse::<u64>().unwrap_or(0),
-            }
-        } else {
-            0
-        };
 
-        // Parse "YYYY-MM-DD HH:MM:SS" manually
-        if let Some(epoch_secs) = parse_datetime_to_epoch(datetime_part) {
-            return (epoch_secs * 1000 + millis as i64) as f64;
+    /// Serialize with `received_at` rendered in the requested output format
+    /// (see `received_at=<format>` on the search endpoints).
+    pub fn to_json(&self, format: crate::time::TimestampFormat) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or(serde_json::Value::Null);
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert(
+                "receivedAt".to_string(),
+                crate::time::render_received_at(self.received_at, format),
+            );
         }
+        value
     }
-    tracing::warn!(value = s, "failed to parse ClickHouse received_at timestamp");
-    0.0
-}
-
-/// Parse "YYYY-MM-DD HH:MM:SS" to epoch seconds.
-fn parse_datetime_to_epoch(s: &str) -> Option<i64> {
-    let bytes = s.as_bytes();
-    if bytes.len() < 19 {
-        return None;
-    }
-
-    let year: i64 = s[0..4].parse().ok()?;
-    let month: i64 = s[5..7].parse().ok()?;
-    let day: i64 = s[8..10].parse().ok()?;
-    let hour: i64 = s[11..13].parse().ok()?;
-    let min: i64 = s[14..16].parse().ok()?;
-    let sec: i64 = s[17..19].parse().ok()?;
-
-    // Validate ranges to prevent panics on malformed data
-    if !(1..=12).contains(&month)
-        || !(1..=31).contains(&day)
-        || !(0..=23).contains(&hour)
-        || !(0..=59).contains(&min)
-        || !(0..=59).contains(&sec)
-    {
-        return None;
-    }
-
-    // Simplified days-from-epoch calculation (no leap second handling)
-    let mut days: i64 = 0;
-    for y in 1970..year {
-        days += if is_leap_year(y) { 366 } else { 365 };
-    }
-    let month_days = [
-        31,
-        28 + i64::from(is_leap_year(year)),
-        31,
-        30,
-        31,
-        30,
-        31,
-        31,
-        30,
-        31,
-        30,
-        31,
-    ];
-    for &d in &month_days[..(month - 1) as usize] {
-        days += d;
-    }
-    days += day - 1;
-
-    Some(days * 86400 + hour * 3600 + min * 60 + sec)
 }
 
-fn is_leap_year(y: i64) -> bool {
-    (y % 4 == 0 && y % 100 != 0) || y % 400 == 0
-}
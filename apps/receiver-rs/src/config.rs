@@ -1,5 +1,12 @@
+use arc_swap::ArcSwap;
 use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
 use std::env;
+use std::sync::Arc;
+
+/// Hot-swappable handle to the current `Config`. Handlers and workers read
+/// through this instead of holding a `Config` by value so `Config::reload()`
+/// can take effect without a restart.
+pub type SharedConfig = Arc<ArcSwap<Config>>;
 
 #[derive(Clone)]
 pub struct Config {
@@ -12,16 +19,104 @@ pub struct Config {
     pub port: u16,
     pub sentry_dsn: Option<String>,
     pub debug: bool,
+    /// Emit `tracing_subscriber`'s JSON formatter instead of the default
+    /// human-readable one — for log pipelines that parse structured fields
+    /// rather than grep plain text. Baked into the subscriber at `main`
+    /// startup, so (like the other connection-shaped settings) this can't be
+    /// changed without a restart — see `changed_cold_fields`.
+    pub log_json: bool,
     pub flush_workers: usize,
     pub batch_max_size: usize,
     pub flush_interval_ms: u64,
+    /// Tranquilizer factor for adaptive flush pacing (see `workers::flush`):
+    /// after a pass that did work, the worker sleeps
+    /// `smoothed_pass_duration * flush_tranquility`, so it stays busy a fixed
+    /// fraction `1/(1+flush_tranquility)` of the time regardless of load.
+    pub flush_tranquility: f64,
+    /// Floor on the tranquilizer's computed sleep, so a near-instant pass
+    /// (tiny batches) doesn't spin the worker in a busy loop.
+    pub flush_pacing_min_ms: u64,
+    /// Ceiling on the tranquilizer's computed sleep, so a pathologically slow
+    /// pass doesn't stall the worker far longer than `flush_interval_ms` would.
+    pub flush_pacing_max_ms: u64,
     pub endpoint_cache_ttl_secs: u64,
     pub quota_cache_ttl_secs: u64,
+    /// TTL for cached `/search` results (`redis::search_cache`) — both the
+    /// Redis tier (read per-write, hot-reloadable like the caches above) and
+    /// the process-local `mini_moka` tier. The local tier's TTL is baked into
+    /// its builder at construction (see `RedisState::new`), so unlike the
+    /// other cache TTLs this one is a cold field — see `changed_cold_fields`.
+    pub search_cache_ttl_secs: u64,
+    /// Max time to wait, on SIGTERM, for the flush buffer to drain before exiting.
+    pub shutdown_grace_secs: u64,
+    /// Burst cap for `RedisState::check_burst_rate`'s sliding-window limiter —
+    /// requests allowed per `rate_limit_window_secs`, on top of (not instead
+    /// of) the monthly quota counter.
+    pub rate_limit_max: u64,
+    /// Window width for the burst rate limiter.
+    pub rate_limit_window_secs: u64,
+    /// Requests per minute allowed on the search endpoints (`/search`,
+    /// `/search/facets`) — applied to every caller, since
+    /// `middleware::search_rate_limit` keys this off the caller's IP rather
+    /// than the request's unauthenticated `?user_id=`/`?plan=` query params.
+    pub search_rate_limit_free_per_min: u64,
+    /// Reserved for a higher per-minute budget for callers on the `pro`
+    /// plan. Not applied yet: `middleware::search_rate_limit` has no
+    /// authoritative way to resolve a caller's plan (the request's `?plan=`
+    /// is unauthenticated), so wiring this in needs that resolution first —
+    /// trusting the query param would let a caller multiply their own budget
+    /// just by claiming `plan=pro`.
+    pub search_rate_limit_pro_per_min: u64,
+    /// Max times `RedisState::requeue` will retry a request that failed to
+    /// flush before moving it to that slug's `buf:dead:{slug}` dead-letter list.
+    pub buffer_retry_max_attempts: u32,
+    /// Starting delay for the buffer retry queue's exponential backoff
+    /// (`base_delay * 2^attempts`, capped at `buffer_retry_cap_ms`).
+    pub buffer_retry_base_delay_ms: u64,
+    /// Cap on the buffer retry queue's backoff delay.
+    pub buffer_retry_cap_ms: u64,
+    /// Max retry attempts for idempotent Convex GETs (`fetch_and_cache_endpoint`,
+    /// `fetch_and_cache_quota`, `list_users_by_plan`) on network errors or 5xx.
+    pub convex_max_retries: u32,
+    /// Starting delay for decorrelated-jitter backoff between Convex GET retries.
+    pub convex_retry_base_ms: u64,
+    /// Cap on the decorrelated-jitter backoff delay between Convex GET retries.
+    pub convex_retry_cap_ms: u64,
+    /// Enable HTTP/2 prior-knowledge (skip the HTTP/1.1 upgrade handshake) for
+    /// the Convex client — only safe if Convex's frontend actually speaks h2c.
+    pub convex_http2_prior_knowledge: bool,
+    /// Enable HTTP/2's adaptive flow-control window for the Convex client.
+    pub convex_http2_adaptive_window: bool,
+    /// TCP connect timeout for the Convex client, separate from the overall
+    /// per-request timeout so a slow-to-connect host fails fast.
+    pub convex_connect_timeout_ms: u64,
+    /// TCP keepalive interval for the Convex client's connection pool.
+    /// `None` disables keepalive probes (today's behavior).
+    pub convex_tcp_keepalive_secs: Option<u64>,
+    /// Max idle connections kept open per host in the Convex client's pool.
+    pub convex_pool_max_idle_per_host: usize,
+    /// How long an idle pooled connection to Convex is kept before closing.
+    pub convex_pool_idle_timeout_secs: u64,
     // ClickHouse (optional — disabled when clickhouse_url is None)
     pub clickhouse_url: Option<String>,
     pub clickhouse_user: String,
     pub clickhouse_password: String,
     pub clickhouse_database: String,
+    /// Gzip-compress `insert_requests` bodies at or above
+    /// `clickhouse_compress_min_body_bytes`, and advertise `Accept-Encoding:
+    /// gzip` on queries (see `ClickHouseClient::new`).
+    pub clickhouse_compress: bool,
+    /// Minimum uncompressed insert-body size, in bytes, before it's worth
+    /// gzip-compressing — small batches skip compression.
+    pub clickhouse_compress_min_body_bytes: usize,
+    /// Directory for the local disk spill store (`crate::spill::SpillStore`),
+    /// sled's on-disk format — opened once at startup, like the ClickHouse
+    /// connection parameters above.
+    pub spill_dir: String,
+    /// Max requests the disk spill store will hold before `handle_webhook`
+    /// starts returning 503 instead of silently accepting more than can ever
+    /// be reconciled back into Redis.
+    pub spill_max_entries: usize,
 }
 
 impl std::fmt::Debug for Config {
@@ -38,15 +133,62 @@ impl std::fmt::Debug for Config {
             .field("redis_db", &self.redis_db)
             .field("port", &self.port)
             .field("debug", &self.debug)
+            .field("log_json", &self.log_json)
             .field("flush_workers", &self.flush_workers)
             .field("batch_max_size", &self.batch_max_size)
             .field("flush_interval_ms", &self.flush_interval_ms)
+            .field("flush_tranquility", &self.flush_tranquility)
+            .field("flush_pacing_min_ms", &self.flush_pacing_min_ms)
+            .field("flush_pacing_max_ms", &self.flush_pacing_max_ms)
             .field("endpoint_cache_ttl_secs", &self.endpoint_cache_ttl_secs)
             .field("quota_cache_ttl_secs", &self.quota_cache_ttl_secs)
+            .field("search_cache_ttl_secs", &self.search_cache_ttl_secs)
+            .field("shutdown_grace_secs", &self.shutdown_grace_secs)
+            .field("rate_limit_max", &self.rate_limit_max)
+            .field("rate_limit_window_secs", &self.rate_limit_window_secs)
+            .field(
+                "search_rate_limit_free_per_min",
+                &self.search_rate_limit_free_per_min,
+            )
+            .field(
+                "search_rate_limit_pro_per_min",
+                &self.search_rate_limit_pro_per_min,
+            )
+            .field("buffer_retry_max_attempts", &self.buffer_retry_max_attempts)
+            .field("buffer_retry_base_delay_ms", &self.buffer_retry_base_delay_ms)
+            .field("buffer_retry_cap_ms", &self.buffer_retry_cap_ms)
+            .field("convex_max_retries", &self.convex_max_retries)
+            .field("convex_retry_base_ms", &self.convex_retry_base_ms)
+            .field("convex_retry_cap_ms", &self.convex_retry_cap_ms)
+            .field(
+                "convex_http2_prior_knowledge",
+                &self.convex_http2_prior_knowledge,
+            )
+            .field(
+                "convex_http2_adaptive_window",
+                &self.convex_http2_adaptive_window,
+            )
+            .field("convex_connect_timeout_ms", &self.convex_connect_timeout_ms)
+            .field("convex_tcp_keepalive_secs", &self.convex_tcp_keepalive_secs)
+            .field(
+                "convex_pool_max_idle_per_host",
+                &self.convex_pool_max_idle_per_host,
+            )
+            .field(
+                "convex_pool_idle_timeout_secs",
+                &self.convex_pool_idle_timeout_secs,
+            )
             .field("clickhouse_url", &self.clickhouse_url)
             .field("clickhouse_user", &self.clickhouse_user)
             .field("clickhouse_password", &"[REDACTED]")
             .field("clickhouse_database", &self.clickhouse_database)
+            .field("clickhouse_compress", &self.clickhouse_compress)
+            .field(
+                "clickhouse_compress_min_body_bytes",
+                &self.clickhouse_compress_min_body_bytes,
+            )
+            .field("spill_dir", &self.spill_dir)
+            .field("spill_max_entries", &self.spill_max_entries)
             .finish()
     }
 }
@@ -64,11 +206,54 @@ fn parse_env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
     }
 }
 
+/// Errors produced while building or reloading a `Config`. Reload-time errors
+/// must never panic — they're reported back to whatever triggered the reload
+/// (SIGHUP handler or the `/admin/reload` endpoint) and the previous config
+/// stays in effect.
+#[derive(Debug)]
+pub enum ConfigError {
+    MissingEnv(&'static str),
+    InvalidClickhouseDatabase,
+    ZeroFlushWorkers,
+    ZeroBatchMaxSize,
+    /// A reload candidate changed one or more fields that require a restart
+    /// (listener port, Redis/ClickHouse connection parameters, worker pool size).
+    ColdFieldsChanged(Vec<&'static str>),
+}
+
+impl std::error::Error for ConfigError {}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::MissingEnv(name) => write!(f, "{name} is required"),
+            ConfigError::InvalidClickhouseDatabase => write!(
+                f,
+                "CLICKHOUSE_DATABASE must contain only alphanumeric characters and underscores"
+            ),
+            ConfigError::ZeroFlushWorkers => write!(f, "FLUSH_WORKERS must be > 0"),
+            ConfigError::ZeroBatchMaxSize => write!(f, "BATCH_MAX_SIZE must be > 0"),
+            ConfigError::ColdFieldsChanged(fields) => write!(
+                f,
+                "reload rejected: restart-only fields changed: {}",
+                fields.join(", ")
+            ),
+        }
+    }
+}
+
 impl Config {
     pub fn from_env() -> Self {
-        let convex_site_url = env::var("CONVEX_SITE_URL").expect("CONVEX_SITE_URL is required");
-        let capture_shared_secret =
-            env::var("CAPTURE_SHARED_SECRET").expect("CAPTURE_SHARED_SECRET is required");
+        Self::from_env_checked().unwrap_or_else(|e| panic!("invalid configuration: {e}"))
+    }
+
+    /// Like `from_env`, but reports errors instead of panicking so it can also
+    /// be used as the candidate-building step of `reload()`.
+    pub fn from_env_checked() -> Result<Self, ConfigError> {
+        let convex_site_url = env::var("CONVEX_SITE_URL")
+            .map_err(|_| ConfigError::MissingEnv("CONVEX_SITE_URL"))?;
+        let capture_shared_secret = env::var("CAPTURE_SHARED_SECRET")
+            .map_err(|_| ConfigError::MissingEnv("CAPTURE_SHARED_SECRET"))?;
 
         let redis_host = env::var("REDIS_HOST").unwrap_or_else(|_| "127.0.0.1".into());
         let redis_port: u16 = parse_env_or("REDIS_PORT", 6380);
@@ -79,12 +264,44 @@ impl Config {
 
         let sentry_dsn = env::var("SENTRY_DSN").ok().filter(|s| !s.is_empty());
         let debug = env::var("RECEIVER_DEBUG").is_ok_and(|v| !v.is_empty());
+        let log_json: bool = parse_env_or("LOG_JSON", false);
 
         let flush_workers: usize = parse_env_or("FLUSH_WORKERS", 4);
         let batch_max_size: usize = parse_env_or("BATCH_MAX_SIZE", 50);
         let flush_interval_ms: u64 = parse_env_or("FLUSH_INTERVAL_MS", 100);
+        let flush_tranquility: f64 = parse_env_or("FLUSH_TRANQUILITY", 0.5);
+        let flush_pacing_min_ms: u64 = parse_env_or("FLUSH_PACING_MIN_MS", 10);
+        let flush_pacing_max_ms: u64 = parse_env_or("FLUSH_PACING_MAX_MS", 5_000);
         let endpoint_cache_ttl_secs: u64 = parse_env_or("ENDPOINT_CACHE_TTL_SECS", 300);
         let quota_cache_ttl_secs: u64 = parse_env_or("QUOTA_CACHE_TTL_SECS", 300);
+        let search_cache_ttl_secs: u64 = parse_env_or("SEARCH_CACHE_TTL_SECS", 30);
+        let shutdown_grace_secs: u64 = parse_env_or("SHUTDOWN_GRACE_SECS", 30);
+        let rate_limit_max: u64 = parse_env_or("RATE_LIMIT_MAX", 20);
+        let rate_limit_window_secs: u64 = parse_env_or("RATE_LIMIT_WINDOW_SECS", 1);
+        let search_rate_limit_free_per_min: u64 =
+            parse_env_or("SEARCH_RATE_LIMIT_FREE_PER_MIN", 30);
+        let search_rate_limit_pro_per_min: u64 =
+            parse_env_or("SEARCH_RATE_LIMIT_PRO_PER_MIN", 300);
+        let buffer_retry_max_attempts: u32 = parse_env_or("BUFFER_RETRY_MAX_ATTEMPTS", 5);
+        let buffer_retry_base_delay_ms: u64 = parse_env_or("BUFFER_RETRY_BASE_DELAY_MS", 1_000);
+        let buffer_retry_cap_ms: u64 = parse_env_or("BUFFER_RETRY_CAP_MS", 60_000);
+
+        let convex_max_retries: u32 = parse_env_or("CONVEX_MAX_RETRIES", 3);
+        let convex_retry_base_ms: u64 = parse_env_or("CONVEX_RETRY_BASE_MS", 100);
+        let convex_retry_cap_ms: u64 = parse_env_or("CONVEX_RETRY_CAP_MS", 5000);
+
+        let convex_http2_prior_knowledge: bool =
+            parse_env_or("CONVEX_HTTP2_PRIOR_KNOWLEDGE", false);
+        let convex_http2_adaptive_window: bool =
+            parse_env_or("CONVEX_HTTP2_ADAPTIVE_WINDOW", false);
+        let convex_connect_timeout_ms: u64 = parse_env_or("CONVEX_CONNECT_TIMEOUT_MS", 10_000);
+        let convex_tcp_keepalive_secs: Option<u64> = env::var("CONVEX_TCP_KEEPALIVE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|secs| *secs > 0);
+        let convex_pool_max_idle_per_host: usize =
+            parse_env_or("CONVEX_POOL_MAX_IDLE_PER_HOST", 100);
+        let convex_pool_idle_timeout_secs: u64 = parse_env_or("CONVEX_POOL_IDLE_TIMEOUT_SECS", 90);
 
         // ClickHouse — optional, disabled when CLICKHOUSE_HOST is empty/unset.
         // Builds URL from CLICKHOUSE_HOST + CLICKHOUSE_PORT (matches Redis pattern).
@@ -97,19 +314,14 @@ impl Config {
         let clickhouse_password = env::var("CLICKHOUSE_PASSWORD").unwrap_or_default();
         let clickhouse_database =
             env::var("CLICKHOUSE_DATABASE").unwrap_or_else(|_| "webhooks".into());
+        let clickhouse_compress: bool = parse_env_or("CLICKHOUSE_COMPRESS", true);
+        let clickhouse_compress_min_body_bytes: usize =
+            parse_env_or("CLICKHOUSE_COMPRESS_MIN_BODY_BYTES", 1024);
 
-        // Validate database name to prevent SQL injection via env var
-        assert!(
-            clickhouse_database
-                .chars()
-                .all(|c| c.is_ascii_alphanumeric() || c == '_'),
-            "CLICKHOUSE_DATABASE must contain only alphanumeric characters and underscores"
-        );
+        let spill_dir = env::var("SPILL_DIR").unwrap_or_else(|_| "./data/spill".into());
+        let spill_max_entries: usize = parse_env_or("SPILL_MAX_ENTRIES", 100_000);
 
-        assert!(flush_workers > 0, "FLUSH_WORKERS must be > 0");
-        assert!(batch_max_size > 0, "BATCH_MAX_SIZE must be > 0");
-
-        Self {
+        let config = Self {
             convex_site_url,
             capture_shared_secret,
             redis_host,
@@ -119,16 +331,126 @@ impl Config {
             port,
             sentry_dsn,
             debug,
+            log_json,
             flush_workers,
             batch_max_size,
             flush_interval_ms,
+            flush_tranquility,
+            flush_pacing_min_ms,
+            flush_pacing_max_ms,
             endpoint_cache_ttl_secs,
             quota_cache_ttl_secs,
+            search_cache_ttl_secs,
+            shutdown_grace_secs,
+            rate_limit_max,
+            rate_limit_window_secs,
+            search_rate_limit_free_per_min,
+            search_rate_limit_pro_per_min,
+            buffer_retry_max_attempts,
+            buffer_retry_base_delay_ms,
+            buffer_retry_cap_ms,
+            convex_max_retries,
+            convex_retry_base_ms,
+            convex_retry_cap_ms,
+            convex_http2_prior_knowledge,
+            convex_http2_adaptive_window,
+            convex_connect_timeout_ms,
+            convex_tcp_keepalive_secs,
+            convex_pool_max_idle_per_host,
+            convex_pool_idle_timeout_secs,
             clickhouse_url,
             clickhouse_user,
             clickhouse_password,
             clickhouse_database,
+            clickhouse_compress,
+            clickhouse_compress_min_body_bytes,
+            spill_dir,
+            spill_max_entries,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Invariants that must hold for both a freshly-loaded config and a
+    /// reload candidate (validated before it's ever swapped in).
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !self
+            .clickhouse_database
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(ConfigError::InvalidClickhouseDatabase);
+        }
+        if self.flush_workers == 0 {
+            return Err(ConfigError::ZeroFlushWorkers);
+        }
+        if self.batch_max_size == 0 {
+            return Err(ConfigError::ZeroBatchMaxSize);
+        }
+        Ok(())
+    }
+
+    /// Re-read the environment and produce a validated replacement config.
+    /// Rejects the reload (without touching `self`) if any field that
+    /// requires a restart — connection parameters, the listener port, or the
+    /// flush worker pool size — would change.
+    pub fn reload(&self) -> Result<Self, ConfigError> {
+        let candidate = Self::from_env_checked()?;
+        let changed = self.changed_cold_fields(&candidate);
+        if !changed.is_empty() {
+            return Err(ConfigError::ColdFieldsChanged(changed));
+        }
+        Ok(candidate)
+    }
+
+    fn changed_cold_fields(&self, other: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.convex_site_url != other.convex_site_url {
+            changed.push("convex_site_url");
+        }
+        if self.capture_shared_secret != other.capture_shared_secret {
+            changed.push("capture_shared_secret");
+        }
+        if self.redis_host != other.redis_host {
+            changed.push("redis_host");
+        }
+        if self.redis_port != other.redis_port {
+            changed.push("redis_port");
+        }
+        if self.redis_password != other.redis_password {
+            changed.push("redis_password");
+        }
+        if self.redis_db != other.redis_db {
+            changed.push("redis_db");
+        }
+        if self.port != other.port {
+            changed.push("port");
+        }
+        if self.flush_workers != other.flush_workers {
+            changed.push("flush_workers");
+        }
+        if self.clickhouse_url != other.clickhouse_url {
+            changed.push("clickhouse_url");
+        }
+        if self.clickhouse_user != other.clickhouse_user {
+            changed.push("clickhouse_user");
+        }
+        if self.clickhouse_password != other.clickhouse_password {
+            changed.push("clickhouse_password");
+        }
+        if self.clickhouse_database != other.clickhouse_database {
+            changed.push("clickhouse_database");
+        }
+        if self.spill_dir != other.spill_dir {
+            changed.push("spill_dir");
+        }
+        if self.search_cache_ttl_secs != other.search_cache_ttl_secs {
+            changed.push("search_cache_ttl_secs");
+        }
+        if self.log_json != other.log_json {
+            changed.push("log_json");
         }
+        changed
     }
 
     pub fn redis_url(&self) -> String {
@@ -1,15 +1,45 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
 use redis::AsyncCommands;
+use tokio::sync::Notify;
 
+use crate::convex::types::now_ms;
 use crate::redis::RedisState;
 
 const STATE_KEY: &str = "cb:state";
+/// Sorted set of recent failure timestamps (ms), scored by timestamp so a
+/// stale tail can be trimmed with `ZREMRANGEBYSCORE` — replaces a plain
+/// `INCR` counter so the threshold reflects a true recent failure *rate*
+/// instead of an all-time-within-the-window total that never shrinks until
+/// the whole key expires.
 const FAILURES_KEY: &str = "cb:failures";
+/// Consecutive circuit-open trips since the last `record_success`, used to
+/// grow the open-state cooldown exponentially (see `RECORD_FAILURE_SCRIPT`).
+const BACKOFF_KEY: &str = "cb:backoff";
 const THRESHOLD: i64 = 5;
+/// Base cooldown for the first trip to open; doubles on each consecutive
+/// trip (see `RECORD_FAILURE_SCRIPT`).
 const COOLDOWN_SECS: i64 = 30;
+/// Caps the exponential growth at `COOLDOWN_SECS * 2^(MAX_BACKOFF_LEVEL-1)`
+/// = 480s, so a persistently flapping endpoint settles at a slow, steady
+/// re-probe rate instead of backing off forever.
+const MAX_BACKOFF_LEVEL: i64 = 5;
+/// `cb:backoff` resets on its own after this long with no new trip, even
+/// without an intervening `record_success` — a flapping endpoint that's
+/// been quiet for an hour shouldn't still pay the accumulated backoff the
+/// next time it has a single blip.
+const BACKOFF_KEY_EXPIRE_SECS: i64 = 3_600;
 const HALF_OPEN_TTL_SECS: i64 = 60;
-const FAILURES_EXPIRE_SECS: i64 = 300; // 5 min
+/// Width of the sliding window `FAILURES_KEY` is trimmed to.
+const FAILURE_WINDOW_MS: i64 = 300_000; // 5 min
+
+/// How often the background task flushes aggregated local deltas to Redis,
+/// and re-checks whether an open circuit's cooldown has elapsed.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(250);
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CircuitState {
     Closed,
     Open,
@@ -26,147 +56,540 @@ impl std::fmt::Display for CircuitState {
     }
 }
 
-/// Lua script for atomic circuit breaker check.
-/// Returns: 1 = allowed, 0 = rejected
-/// Logic:
-///   - closed -> always allow
-///   - open -> check cooldown, transition to half-open if expired (with TTL)
-///   - half-open -> allow exactly one probe (via SETNX on cb:probe)
-const ALLOW_REQUEST_SCRIPT: &str = r#"
-local state = redis.call('GET', KEYS[1])
-if state == false or state == 'closed' then
-    return 1
-end
+impl CircuitState {
+    fn to_u8(self) -> u8 {
+        match self {
+            CircuitState::Closed => 0,
+            CircuitState::Open => 1,
+            CircuitState::HalfOpen => 2,
+        }
+    }
 
-if state == 'open' then
-    local ttl = redis.call('TTL', KEYS[1])
-    if ttl <= 0 then
-        redis.call('SET', KEYS[1], 'half-open', 'EX', tonumber(ARGV[1]))
-        redis.call('SET', KEYS[2], '1', 'EX', 30, 'NX')
-        return 1
-    end
-    return 0
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => CircuitState::Open,
+            2 => CircuitState::HalfOpen,
+            _ => CircuitState::Closed,
+        }
+    }
+}
+
+/// Lua script for atomic failure recording. Trims `cb:failures` to the
+/// sliding window, adds this flush's failures to it, and — on a threshold
+/// crossing or a failed half-open probe — bumps `cb:backoff` and opens the
+/// circuit for an exponentially grown cooldown
+/// (`base_cooldown_secs * 2^(level-1)`, level capped at `max_backoff_level`).
+/// KEYS[1] = cb:state, KEYS[2] = cb:failures, KEYS[3] = cb:probe, KEYS[4] = cb:backoff
+/// ARGV[1] = failure count delta, ARGV[2] = threshold, ARGV[3] = base_cooldown_secs,
+/// ARGV[4] = window_ms, ARGV[5] = now_ms, ARGV[6] = max_backoff_level,
+/// ARGV[7] = backoff_key_expire_secs
+/// Returns: recent failure count (sliding window) after this flush's adds.
+const RECORD_FAILURE_SCRIPT: &str = r#"
+local now = tonumber(ARGV[5])
+redis.call('ZREMRANGEBYSCORE', KEYS[2], '-inf', now - tonumber(ARGV[4]))
+for i = 1, tonumber(ARGV[1]) do
+    redis.call('ZADD', KEYS[2], now, tostring(now) .. ':' .. tostring(i))
 end
+redis.call('EXPIRE', KEYS[2], math.ceil(tonumber(ARGV[4]) / 1000))
+redis.call('DEL', KEYS[3])
 
-if state == 'half-open' then
-    local probe = redis.call('SET', KEYS[2], '1', 'EX', 30, 'NX')
-    if probe then
-        return 1
+local recent = redis.call('ZCARD', KEYS[2])
+local state = redis.call('GET', KEYS[1])
+
+if recent >= tonumber(ARGV[2]) or state == 'half-open' then
+    local level = redis.call('INCR', KEYS[4])
+    redis.call('EXPIRE', KEYS[4], tonumber(ARGV[7]))
+    if level > tonumber(ARGV[6]) then
+        level = tonumber(ARGV[6])
     end
-    return 0
+    local cooldown = tonumber(ARGV[3]) * math.pow(2, level - 1)
+    redis.call('SET', KEYS[1], 'open', 'EX', math.floor(cooldown))
 end
 
-return 1
+return recent
 "#;
 
-/// Lua script for atomic failure recording.
-/// KEYS[1] = cb:state, KEYS[2] = cb:failures, KEYS[3] = cb:probe
-/// ARGV[1] = threshold, ARGV[2] = cooldown_secs, ARGV[3] = failures_expire_secs
-/// Returns: failure count after increment
-const RECORD_FAILURE_SCRIPT: &str = r#"
-local count = redis.call('INCR', KEYS[2])
-redis.call('EXPIRE', KEYS[2], tonumber(ARGV[3]))
-redis.call('DEL', KEYS[3])
+/// Lua script that re-checks an open circuit's cooldown, flips it to
+/// half-open once the TTL has elapsed, and atomically claims the
+/// fleet-wide single-probe slot (`cb:probe`, via `SET ... NX`) on every call
+/// that observes half-open — so exactly one instance across the whole fleet
+/// wins it, the same invariant the old centralized `SET cb:probe NX` gave
+/// when every call went straight to Redis. `CircuitBreaker::apply_local_state`
+/// only *acts* on the won-probe flag the first time it locally observes the
+/// open -> half-open transition (its own `previous != HalfOpen` check), so a
+/// winning instance's later flushes re-losing the already-claimed key to
+/// itself doesn't re-lock out the request it already let through.
+/// Returns `"<state>:<backoff level>:<1 or 0, this call's probe win>"`.
+/// KEYS[1] = cb:state, KEYS[2] = cb:backoff, KEYS[3] = cb:probe,
+/// ARGV[1] = half_open_ttl_secs
+const REFRESH_STATE_SCRIPT: &str = r#"
+local level = tonumber(redis.call('GET', KEYS[2])) or 0
+local state = redis.call('GET', KEYS[1])
+if state == false then
+    return 'closed:' .. level .. ':0'
+end
 
-if count >= tonumber(ARGV[1]) then
-    redis.call('SET', KEYS[1], 'open', 'EX', tonumber(ARGV[2]))
-    return count
+if state == 'open' then
+    local ttl = redis.call('TTL', KEYS[1])
+    if ttl <= 0 then
+        redis.call('SET', KEYS[1], 'half-open', 'EX', tonumber(ARGV[1]))
+        local won = redis.call('SET', KEYS[3], '1', 'EX', tonumber(ARGV[1]), 'NX')
+        return 'half-open:' .. level .. ':' .. (won and '1' or '0')
+    end
+    return 'open:' .. level .. ':0'
 end
 
-local state = redis.call('GET', KEYS[1])
 if state == 'half-open' then
-    redis.call('SET', KEYS[1], 'open', 'EX', tonumber(ARGV[2]))
+    local won = redis.call('SET', KEYS[3], '1', 'EX', tonumber(ARGV[1]), 'NX')
+    return 'half-open:' .. level .. ':' .. (won and '1' or '0')
 end
 
-return count
+return state .. ':' .. level .. ':0'
 "#;
 
+/// In-process accounting shared by every clone of a `CircuitBreaker`. Calls
+/// increment these atomics directly (no Redis round trip, no spawned task);
+/// a single background task periodically folds the accumulated deltas into
+/// the shared Redis state and refreshes `state` from the result, so
+/// `allow_request` can answer from memory instead of blocking on Redis.
+struct LocalCircuitState {
+    state: AtomicU8,
+    pending_successes: AtomicU64,
+    pending_failures: AtomicU64,
+    /// Gates the single probe request a locally half-open breaker lets
+    /// through. Set to `false` (armed) only when this instance's flush wins
+    /// the fleet-wide `cb:probe` SETNX on the open -> half-open transition
+    /// (see `REFRESH_STATE_SCRIPT`); every other instance — and every other
+    /// instance's own later flushes — gets `true` (locked), so at most one
+    /// request across the whole fleet is ever let through during a half-open
+    /// window.
+    half_open_probe_taken: AtomicBool,
+    /// Consecutive open trips since the last `record_success`, mirrored from
+    /// `cb:backoff` on every flush — see `CircuitBreaker::backoff_level`.
+    backoff_level: AtomicU32,
+    /// Wakes the flush task immediately on a failure-threshold crossing
+    /// instead of waiting out the rest of the current `FLUSH_INTERVAL`.
+    flush_now: Notify,
+}
+
 #[derive(Clone)]
 pub struct CircuitBreaker {
     pub(crate) redis: RedisState,
+    local: Arc<LocalCircuitState>,
 }
 
 impl CircuitBreaker {
     pub fn new(redis: RedisState) -> Self {
-        Self { redis }
+        let local = Arc::new(LocalCircuitState {
+            state: AtomicU8::new(CircuitState::Closed.to_u8()),
+            pending_successes: AtomicU64::new(0),
+            pending_failures: AtomicU64::new(0),
+            half_open_probe_taken: AtomicBool::new(false),
+            backoff_level: AtomicU32::new(0),
+            flush_now: Notify::new(),
+        });
+
+        let breaker = Self { redis, local };
+        breaker.spawn_flush_loop();
+        breaker
+    }
+
+    fn spawn_flush_loop(&self) {
+        let breaker = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = breaker.local.flush_now.notified() => {}
+                }
+                breaker.flush_once().await;
+            }
+        });
+    }
+
+    fn cached_state(&self) -> CircuitState {
+        CircuitState::from_u8(self.local.state.load(Ordering::Acquire))
     }
 
     /// Check if a request should be allowed through the circuit breaker.
-    pub async fn allow_request(&self) -> bool {
-        let mut conn = self.redis.conn.clone();
-        let result: Result<i64, _> = redis::Script::new(ALLOW_REQUEST_SCRIPT)
-            .key(STATE_KEY)
-            .key("cb:probe")
-            .arg(HALF_OPEN_TTL_SECS)
-            .invoke_async(&mut conn)
-            .await;
+    /// Answers from local state only — no Redis call on the request path.
+    pub fn allow_request(&self) -> bool {
+        match self.cached_state() {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => self
+                .local
+                .half_open_probe_taken
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok(),
+        }
+    }
 
-        match result {
-            Ok(1) => true,
-            Ok(0) => false,
-            Ok(_) => true, // unexpected value -> fail-open
-            Err(e) => {
-                tracing::warn!(error = %e, "circuit breaker Redis error, failing open");
-                true
-            }
+    /// Record a successful request. Purely a local counter bump — picked up
+    /// by the next flush.
+    pub fn record_success(&self) {
+        self.local.pending_successes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a failed request. Purely a local counter bump, but wakes the
+    /// flush task immediately once the local tally reaches `THRESHOLD`, so a
+    /// sudden burst still opens the circuit promptly instead of waiting for
+    /// the next scheduled tick.
+    pub fn record_failure(&self) {
+        let pending = self.local.pending_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if pending >= THRESHOLD as u64 {
+            self.local.flush_now.notify_one();
+        }
+    }
+
+    /// Get the current (locally cached) circuit state.
+    pub fn state(&self) -> CircuitState {
+        self.cached_state()
+    }
+
+    /// Returns true if the circuit is not closed (degraded).
+    pub fn is_degraded(&self) -> bool {
+        self.cached_state() != CircuitState::Closed
+    }
+
+    /// Current consecutive-open-trip count (locally cached, refreshed on
+    /// every flush), for dashboards/logging — not consulted by
+    /// `allow_request`. `0` once `record_success` has cleared `cb:backoff`.
+    pub fn backoff_level(&self) -> u32 {
+        self.local.backoff_level.load(Ordering::Acquire)
+    }
+
+    /// Fold accumulated local deltas into the shared Redis state, then
+    /// refresh the local cache from the result. Called on every tick of the
+    /// background flush loop.
+    async fn flush_once(&self) {
+        let successes = self.local.pending_successes.swap(0, Ordering::AcqRel);
+        let failures = self.local.pending_failures.swap(0, Ordering::AcqRel);
+
+        // A success in this window takes precedence over failures accumulated
+        // in the same window, mirroring the old per-call order where every
+        // success immediately reset the failure count. Applied once per
+        // flush instead of once per request.
+        if successes > 0 {
+            self.flush_success().await;
+        } else if failures > 0 {
+            self.flush_failures(failures).await;
         }
+
+        let (state, backoff_level, won_probe) = self.refresh_redis_state().await;
+        self.apply_local_state(state, backoff_level, won_probe);
     }
 
-    /// Record a successful request — close the circuit.
-    pub async fn record_success(&self) {
+    async fn flush_success(&self) {
         let mut conn = self.redis.conn.clone();
         let _: Result<(), _> = redis::pipe()
             .set(STATE_KEY, "closed")
             .ignore()
             .del(FAILURES_KEY)
             .ignore()
+            .del(BACKOFF_KEY)
+            .ignore()
             .del("cb:probe")
             .ignore()
             .query_async(&mut conn)
             .await;
     }
 
-    /// Record a failed request — atomically increment failures and open circuit at threshold.
-    pub async fn record_failure(&self) {
+    async fn flush_failures(&self, count: u64) {
         let mut conn = self.redis.conn.clone();
 
         let result: Result<i64, _> = redis::Script::new(RECORD_FAILURE_SCRIPT)
             .key(STATE_KEY)
             .key(FAILURES_KEY)
             .key("cb:probe")
+            .key(BACKOFF_KEY)
+            .arg(count as i64)
             .arg(THRESHOLD)
             .arg(COOLDOWN_SECS)
-            .arg(FAILURES_EXPIRE_SECS)
+            .arg(FAILURE_WINDOW_MS)
+            .arg(now_ms())
+            .arg(MAX_BACKOFF_LEVEL)
+            .arg(BACKOFF_KEY_EXPIRE_SECS)
             .invoke_async(&mut conn)
             .await;
 
-        if let Ok(count) = result
-            && count >= THRESHOLD
+        if let Ok(recent) = result
+            && recent >= THRESHOLD
         {
             tracing::warn!(
-                failures = count,
-                "circuit breaker opened after {} consecutive failures",
-                count
+                failures = recent,
+                "circuit breaker opened after {} failures within the sliding window",
+                recent
             );
         }
     }
 
-    /// Get the current circuit state.
-    pub async fn state(&self) -> CircuitState {
+    /// Returns `(state, backoff_level, won_probe)` — `won_probe` is this
+    /// call's result from `REFRESH_STATE_SCRIPT`'s `cb:probe` SETNX attempt,
+    /// meaningful only when `state` is `HalfOpen`.
+    async fn refresh_redis_state(&self) -> (CircuitState, u32, bool) {
         let mut conn = self.redis.conn.clone();
-        let state: Result<Option<String>, _> = conn.get(STATE_KEY).await;
-        match state {
-            Ok(Some(s)) => match s.as_str() {
-                "open" => CircuitState::Open,
-                "half-open" => CircuitState::HalfOpen,
-                _ => CircuitState::Closed,
-            },
-            _ => CircuitState::Closed,
+        let result: Result<String, _> = redis::Script::new(REFRESH_STATE_SCRIPT)
+            .key(STATE_KEY)
+            .key(BACKOFF_KEY)
+            .key("cb:probe")
+            .arg(HALF_OPEN_TTL_SECS)
+            .invoke_async(&mut conn)
+            .await;
+
+        match result {
+            Ok(s) => {
+                let mut parts = s.split(':');
+                let state_str = parts.next().unwrap_or("closed");
+                let level_str = parts.next().unwrap_or("0");
+                let won_str = parts.next().unwrap_or("0");
+                let state = match state_str {
+                    "open" => CircuitState::Open,
+                    "half-open" => CircuitState::HalfOpen,
+                    _ => CircuitState::Closed,
+                };
+                let level = level_str.parse().unwrap_or(0);
+                (state, level, won_str == "1")
+            }
+            Err(e) => {
+                // Keep whatever we had cached rather than guessing — an
+                // isolated Redis hiccup during a flush shouldn't flip an open
+                // circuit closed (or vice versa).
+                tracing::warn!(error = %e, "circuit breaker Redis error during flush, keeping cached state");
+                (self.cached_state(), self.backoff_level(), false)
+            }
         }
     }
 
-    /// Returns true if the circuit is not closed (degraded).
-    pub async fn is_degraded(&self) -> bool {
-        self.state().await != CircuitState::Closed
+    fn apply_local_state(&self, state: CircuitState, backoff_level: u32, won_probe: bool) {
+        let previous = self.cached_state();
+        self.local.state.store(state.to_u8(), Ordering::Release);
+        self.local.backoff_level.store(backoff_level, Ordering::Release);
+        if state == CircuitState::HalfOpen && previous != CircuitState::HalfOpen {
+            // `won_probe` is this flush's fleet-wide `cb:probe` SETNX result —
+            // only the one instance that wins it gets to arm its local probe
+            // gate; every other instance locks its out so `allow_request`
+            // can't also let a request through locally.
+            self.local.half_open_probe_taken.store(!won_probe, Ordering::Release);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Exercises the local accounting in isolation (no Redis, no background
+    /// task) by driving `LocalCircuitState` directly through the same
+    /// transitions `flush_once` applies, to confirm aggregated deltas trip
+    /// and clear the circuit the same way the old per-call path did.
+    fn local() -> LocalCircuitState {
+        LocalCircuitState {
+            state: AtomicU8::new(CircuitState::Closed.to_u8()),
+            pending_successes: AtomicU64::new(0),
+            pending_failures: AtomicU64::new(0),
+            half_open_probe_taken: AtomicBool::new(false),
+            backoff_level: AtomicU32::new(0),
+            flush_now: Notify::new(),
+        }
+    }
+
+    fn cached(local: &LocalCircuitState) -> CircuitState {
+        CircuitState::from_u8(local.state.load(Ordering::Acquire))
+    }
+
+    /// Applies the same precedence `flush_once` uses (success beats failures
+    /// in the same window) against an in-memory failure counter, without
+    /// touching Redis.
+    fn apply_flush(local: &LocalCircuitState, total_failures: &mut i64) {
+        let successes = local.pending_successes.swap(0, Ordering::AcqRel);
+        let failures = local.pending_failures.swap(0, Ordering::AcqRel);
+
+        let new_state = if successes > 0 {
+            *total_failures = 0;
+            CircuitState::Closed
+        } else if failures > 0 {
+            *total_failures += failures as i64;
+            if *total_failures >= THRESHOLD {
+                CircuitState::Open
+            } else {
+                cached(local)
+            }
+        } else {
+            cached(local)
+        };
+
+        let previous = cached(local);
+        local.state.store(new_state.to_u8(), Ordering::Release);
+        if new_state == CircuitState::HalfOpen && previous != CircuitState::HalfOpen {
+            local.half_open_probe_taken.store(false, Ordering::Release);
+        }
+    }
+
+    #[test]
+    fn stays_closed_under_threshold() {
+        let local = local();
+        let mut failures = 0;
+        for _ in 0..(THRESHOLD - 1) {
+            local.pending_failures.fetch_add(1, Ordering::Relaxed);
+        }
+        apply_flush(&local, &mut failures);
+        assert_eq!(cached(&local), CircuitState::Closed);
+    }
+
+    #[test]
+    fn opens_once_accumulated_failures_reach_threshold_in_one_flush() {
+        let local = local();
+        let mut failures = 0;
+        for _ in 0..THRESHOLD {
+            local.pending_failures.fetch_add(1, Ordering::Relaxed);
+        }
+        apply_flush(&local, &mut failures);
+        assert_eq!(cached(&local), CircuitState::Open);
+    }
+
+    #[test]
+    fn opens_across_multiple_flushes_once_threshold_crossed() {
+        let local = local();
+        let mut failures = 0;
+
+        local.pending_failures.fetch_add((THRESHOLD - 2) as u64, Ordering::Relaxed);
+        apply_flush(&local, &mut failures);
+        assert_eq!(cached(&local), CircuitState::Closed);
+
+        local.pending_failures.fetch_add(2, Ordering::Relaxed);
+        apply_flush(&local, &mut failures);
+        assert_eq!(cached(&local), CircuitState::Open);
+    }
+
+    #[test]
+    fn success_resets_accumulated_failures() {
+        let local = local();
+        let mut failures = 0;
+
+        local.pending_failures.fetch_add((THRESHOLD - 1) as u64, Ordering::Relaxed);
+        apply_flush(&local, &mut failures);
+        assert_eq!(cached(&local), CircuitState::Closed);
+        assert_eq!(failures, THRESHOLD - 1);
+
+        local.pending_successes.fetch_add(1, Ordering::Relaxed);
+        apply_flush(&local, &mut failures);
+        assert_eq!(cached(&local), CircuitState::Closed);
+        assert_eq!(failures, 0);
+    }
+
+    /// Mirrors `apply_local_state`'s open -> half-open branch: only arms the
+    /// local probe gate (`half_open_probe_taken = false`) when `won_probe`
+    /// mimics a winning `REFRESH_STATE_SCRIPT` `cb:probe` SETNX result, and
+    /// only on the instance's own first observed transition.
+    fn apply_transition(local: &LocalCircuitState, won_probe: bool) {
+        let previous = cached(local);
+        local.state.store(CircuitState::HalfOpen.to_u8(), Ordering::Release);
+        if previous != CircuitState::HalfOpen {
+            local.half_open_probe_taken.store(!won_probe, Ordering::Release);
+        }
+    }
+
+    #[test]
+    fn half_open_allows_exactly_one_local_probe_when_this_instance_wins() {
+        let local = local();
+        local.state.store(CircuitState::Open.to_u8(), Ordering::Release);
+
+        apply_transition(&local, true);
+
+        assert!(
+            local
+                .half_open_probe_taken
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        );
+        assert!(
+            local
+                .half_open_probe_taken
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn half_open_blocks_every_local_request_when_this_instance_loses_the_probe() {
+        let local = local();
+        local.state.store(CircuitState::Open.to_u8(), Ordering::Release);
+
+        // Another instance's flush won the fleet-wide `cb:probe` SETNX first.
+        apply_transition(&local, false);
+
+        assert!(
+            local
+                .half_open_probe_taken
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                .is_err(),
+            "an instance that lost the fleet-wide probe must not let any local request through"
+        );
+    }
+
+    /// Regression test for the fleet-coordination bug: with N running
+    /// instances independently resetting `half_open_probe_taken` on their
+    /// own Open -> HalfOpen transition, all N would let a request through —
+    /// up to N concurrent probes hitting a recovering Convex endpoint
+    /// instead of the single fleet-wide probe `cb:probe`'s SETNX is meant to
+    /// guarantee. Simulates two instances observing the same transition,
+    /// only one of which wins the (simulated) Redis-level SETNX.
+    #[test]
+    fn fleet_wide_half_open_probe_allows_exactly_one_instance() {
+        let winner = local();
+        let loser = local();
+        winner.state.store(CircuitState::Open.to_u8(), Ordering::Release);
+        loser.state.store(CircuitState::Open.to_u8(), Ordering::Release);
+
+        apply_transition(&winner, true);
+        apply_transition(&loser, false);
+
+        let winner_probed = winner
+            .half_open_probe_taken
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok();
+        let loser_probed = loser
+            .half_open_probe_taken
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok();
+
+        assert!(winner_probed, "the instance that won the fleet-wide probe should let one request through");
+        assert!(!loser_probed, "the instance that lost the fleet-wide probe must not also let one through");
+    }
+
+    #[test]
+    fn record_failure_notifies_flush_task_on_threshold_crossing() {
+        let local = local();
+        for i in 0..THRESHOLD {
+            let pending = local.pending_failures.fetch_add(1, Ordering::Relaxed) + 1;
+            if pending >= THRESHOLD as u64 {
+                local.flush_now.notify_one();
+            }
+            assert_eq!(pending, (i + 1) as u64);
+        }
+        // notify_one() above should have fired without panicking even though
+        // nothing is awaiting `notified()` yet in this test.
+    }
+
+    /// Mirrors the cooldown formula `RECORD_FAILURE_SCRIPT` applies in Lua
+    /// (`base * 2^(level-1)`, capped at `MAX_BACKOFF_LEVEL`), so a change to
+    /// one is caught without needing a live Redis to exercise the script.
+    fn cooldown_for_level(level: i64) -> i64 {
+        let level = level.min(MAX_BACKOFF_LEVEL);
+        COOLDOWN_SECS * 2i64.pow((level - 1) as u32)
+    }
+
+    #[test]
+    fn backoff_cooldown_doubles_and_caps() {
+        assert_eq!(cooldown_for_level(1), 30);
+        assert_eq!(cooldown_for_level(2), 60);
+        assert_eq!(cooldown_for_level(3), 120);
+        assert_eq!(cooldown_for_level(4), 240);
+        assert_eq!(cooldown_for_level(5), 480);
+        // Beyond MAX_BACKOFF_LEVEL the cooldown stays flat at the ceiling.
+        assert_eq!(cooldown_for_level(6), 480);
+        assert_eq!(cooldown_for_level(100), 480);
     }
 }
@@ -1,14 +1,36 @@
 use reqwest::Client;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
 use super::circuit_breaker::CircuitBreaker;
 use super::types::*;
 use crate::config::Config;
+use crate::redact::RedactionCache;
 use crate::redis::RedisState;
 
 const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
 const MAX_RESPONSE_SIZE: usize = 1024 * 1024; // 1MB
 
+/// Decorrelated-jitter retry parameters for idempotent Convex GETs, snapshotted
+/// from `Config` at client construction (same "cold" lifetime as `base_url`/`secret`).
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    cap: Duration,
+}
+
+impl RetryPolicy {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            max_retries: config.convex_max_retries,
+            base_delay: Duration::from_millis(config.convex_retry_base_ms),
+            cap: Duration::from_millis(config.convex_retry_cap_ms),
+        }
+    }
+}
+
 /// Convex HTTP client with circuit breaker.
 #[derive(Clone)]
 pub struct ConvexClient {
@@ -17,16 +39,29 @@ pub struct ConvexClient {
     secret: String,
     circuit: CircuitBreaker,
     redis: RedisState,
+    retry: RetryPolicy,
+    /// Per-slug parsed redaction rule cache (see `crate::redact::RedactionCache`),
+    /// consulted once per `capture_batch` call.
+    redact: RedactionCache,
 }
 
 impl ConvexClient {
     pub fn new(config: &Config, redis: RedisState) -> Self {
-        let http = Client::builder()
+        let mut builder = Client::builder()
             .timeout(HTTP_TIMEOUT)
-            .pool_max_idle_per_host(100)
-            .pool_idle_timeout(Duration::from_secs(90))
-            .build()
-            .expect("failed to create HTTP client");
+            .connect_timeout(Duration::from_millis(config.convex_connect_timeout_ms))
+            .pool_max_idle_per_host(config.convex_pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(config.convex_pool_idle_timeout_secs))
+            .tcp_keepalive(config.convex_tcp_keepalive_secs.map(Duration::from_secs));
+
+        if config.convex_http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if config.convex_http2_adaptive_window {
+            builder = builder.http2_adaptive_window(true);
+        }
+
+        let http = builder.build().expect("failed to create HTTP client");
 
         let circuit = CircuitBreaker::new(redis.clone());
 
@@ -36,6 +71,8 @@ impl ConvexClient {
             secret: config.capture_shared_secret.clone(),
             circuit,
             redis,
+            retry: RetryPolicy::from_config(config),
+            redact: RedactionCache::new(),
         }
     }
 
@@ -43,10 +80,27 @@ impl ConvexClient {
         &self.circuit
     }
 
+    /// Time a `ConvexClient` method call and record it to `crate::metrics`
+    /// (request count by outcome, latency, and error variant on failure).
+    /// Each public method wraps its existing body in this so instrumentation
+    /// stays out of the call logic itself.
+    async fn instrumented<T, Fut>(&self, method: &'static str, fut: Fut) -> Result<T, ConvexError>
+    where
+        Fut: Future<Output = Result<T, ConvexError>>,
+    {
+        let start = std::time::Instant::now();
+        let result = fut.await;
+        crate::metrics::record_convex_call(method, &result, start.elapsed());
+        result
+    }
+
     /// Read the response body with size limiting to prevent unbounded allocation.
-    /// Uses bytes() and checks length before converting to string, which also
-    /// handles chunked responses that lack a Content-Length header.
-    async fn read_body(&self, resp: reqwest::Response) -> Result<(u16, String), ConvexError> {
+    /// Streams via `chunk()` and checks the running total after each chunk, so a
+    /// chunked response with no Content-Length aborts as soon as it exceeds
+    /// `MAX_RESPONSE_SIZE` instead of buffering the whole thing first — peak
+    /// allocation is bounded to `MAX_RESPONSE_SIZE + one_chunk` regardless of
+    /// what the server sends.
+    async fn read_body(&self, mut resp: reqwest::Response) -> Result<(u16, String), ConvexError> {
         let status = resp.status().as_u16();
 
         // Pre-check Content-Length header to reject obviously too-large responses
@@ -58,35 +112,30 @@ impl ConvexClient {
             return Err(ConvexError::ResponseTooLarge);
         }
 
-        // Read as bytes first — reqwest limits to Content-Length when present,
-        // but for chunked responses we check the accumulated size after download.
-        let body_bytes = resp.bytes().await.map_err(|e| {
+        let mut body_bytes = Vec::new();
+        while let Some(chunk) = resp.chunk().await.map_err(|e| {
             self.record_failure_sync();
             ConvexError::Network(e.to_string())
-        })?;
-
-        if body_bytes.len() > MAX_RESPONSE_SIZE {
-            self.record_failure_sync();
-            return Err(ConvexError::ResponseTooLarge);
+        })? {
+            body_bytes.extend_from_slice(&chunk);
+            if body_bytes.len() > MAX_RESPONSE_SIZE {
+                self.record_failure_sync();
+                return Err(ConvexError::ResponseTooLarge);
+            }
         }
 
         let body = String::from_utf8_lossy(&body_bytes).into_owned();
         Ok((status, body))
     }
 
-    /// Fetch endpoint info from Convex and cache it in Redis.
-    pub async fn fetch_and_cache_endpoint(
-        &self,
-        slug: &str,
-    ) -> Result<Option<EndpointInfo>, ConvexError> {
-        if !self.circuit.allow_request().await {
-            return Err(ConvexError::CircuitOpen);
-        }
-
+    /// Run a single idempotent GET attempt: send, read the body, and classify
+    /// the result, recording circuit successes/failures as it goes. Shared by
+    /// every retryable GET so `get_with_retry` only has to manage the loop.
+    async fn do_get(&self, path: &str, query: &[(&str, &str)]) -> Result<(u16, String), ConvexError> {
         let resp = self
             .http
-            .get(format!("{}/endpoint-info", self.base_url))
-            .query(&[("slug", slug)])
+            .get(format!("{}{}", self.base_url, path))
+            .query(query)
             .header("Authorization", format!("Bearer {}", self.secret))
             .send()
             .await
@@ -104,117 +153,153 @@ impl ConvexClient {
 
         // Reachable (even on 4xx) — clear circuit
         self.record_success_sync();
+        Ok((status, body))
+    }
 
-        if !(200..300).contains(&status) {
-            return Err(ConvexError::ClientError(status, body));
-        }
+    /// Retry an idempotent GET attempt on `ConvexError::Network`/`ServerError`
+    /// with AWS-style decorrelated jitter, re-checking `circuit.allow_request()`
+    /// before every attempt (including retries) so an open circuit short-circuits
+    /// the loop instead of sleeping through it. POSTs (e.g. `capture_batch`) are
+    /// not idempotent and stay single-shot, relying on the circuit breaker alone.
+    async fn get_with_retry<F, Fut>(&self, mut attempt: F) -> Result<(u16, String), ConvexError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<(u16, String), ConvexError>>,
+    {
+        let mut sleep = self.retry.base_delay;
+        let mut tries = 0;
+
+        loop {
+            if !self.circuit.allow_request() {
+                return Err(ConvexError::CircuitOpen);
+            }
 
-        let info: EndpointInfo =
-            serde_json::from_str(&body).map_err(|e| ConvexError::ParseError(e.to_string()))?;
+            let result = attempt().await;
 
-        // Cache valid responses; skip caching errors (not_found, etc.)
-        if info.error.is_empty() {
-            self.redis.set_endpoint(slug, &info).await;
-        }
+            let retryable = matches!(
+                result,
+                Err(ConvexError::Network(_)) | Err(ConvexError::ServerError(_, _))
+            );
 
-        if info.error == "not_found" {
-            return Ok(None);
-        }
+            if !retryable || tries >= self.retry.max_retries {
+                return result;
+            }
 
-        Ok(Some(info))
+            tries += 1;
+            sleep = next_decorrelated_delay(sleep, self.retry.base_delay, self.retry.cap);
+            tracing::debug!(
+                attempt = tries,
+                delay_ms = sleep.as_millis() as u64,
+                "retrying Convex GET"
+            );
+            tokio::time::sleep(sleep).await;
+        }
     }
 
-    /// Fetch quota from Convex and cache it in Redis.
-    pub async fn fetch_and_cache_quota(&self, slug: &str) -> Result<(), ConvexError> {
-        if !self.circuit.allow_request().await {
-            return Err(ConvexError::CircuitOpen);
-        }
+    /// Fetch endpoint info from Convex and cache it in Redis.
+    pub async fn fetch_and_cache_endpoint(
+        &self,
+        slug: &str,
+    ) -> Result<Option<EndpointInfo>, ConvexError> {
+        self.instrumented("fetch_and_cache_endpoint", async {
+            let (status, body) = self
+                .get_with_retry(|| self.do_get("/endpoint-info", &[("slug", slug)]))
+                .await?;
 
-        let resp = self
-            .http
-            .get(format!("{}/quota", self.base_url))
-            .query(&[("slug", slug)])
-            .header("Authorization", format!("Bearer {}", self.secret))
-            .send()
-            .await
-            .map_err(|e| {
-                self.record_failure_sync();
-                ConvexError::Network(e.to_string())
-            })?;
+            if !(200..300).contains(&status) {
+                return Err(ConvexError::ClientError(status, body));
+            }
 
-        let (status, body) = self.read_body(resp).await?;
+            let info: EndpointInfo = serde_json::from_str(&body)
+                .map_err(|e| ConvexError::ParseError(e.to_string()))?;
 
-        if status >= 500 {
-            self.record_failure_sync();
-            return Err(ConvexError::ServerError(status, body));
-        }
+            // Cache valid responses; skip caching errors (not_found, etc.)
+            if info.error.is_empty() {
+                self.redis.set_endpoint(slug, &info).await;
+            }
 
-        self.record_success_sync();
+            if info.error == "not_found" {
+                return Ok(None);
+            }
 
-        if !(200..300).contains(&status) {
-            return Err(ConvexError::ClientError(status, body));
-        }
+            Ok(Some(info))
+        })
+        .await
+    }
 
-        let quota: QuotaResponse =
-            serde_json::from_str(&body).map_err(|e| ConvexError::ParseError(e.to_string()))?;
+    /// Fetch quota from Convex and cache it in Redis.
+    pub async fn fetch_and_cache_quota(&self, slug: &str) -> Result<(), ConvexError> {
+        self.instrumented("fetch_and_cache_quota", async {
+            let (status, body) = self
+                .get_with_retry(|| self.do_get("/quota", &[("slug", slug)]))
+                .await?;
 
-        if quota.error == "not_found" {
-            return Ok(());
-        }
+            if !(200..300).contains(&status) {
+                return Err(ConvexError::ClientError(status, body));
+            }
 
-        let user_id = quota.user_id.as_deref().unwrap_or("");
+            let quota: QuotaResponse = serde_json::from_str(&body)
+                .map_err(|e| ConvexError::ParseError(e.to_string()))?;
 
-        // Handle free users needing period start
-        if quota.needs_period_start
-            && !user_id.is_empty()
-            && let Ok(period) = self.call_check_period(user_id).await
-        {
-            if period.error.is_empty() {
-                self.redis
-                    .set_quota(
-                        slug,
-                        period.remaining,
-                        period.limit,
-                        period.period_end.unwrap_or(0),
-                        false,
-                        user_id,
-                    )
-                    .await;
-                return Ok(());
-            } else if period.error == "quota_exceeded" {
-                self.redis
-                    .set_quota(
-                        slug,
-                        0,
-                        period.limit,
-                        period.period_end.unwrap_or(0),
-                        false,
-                        user_id,
-                    )
-                    .await;
+            if quota.error == "not_found" {
                 return Ok(());
             }
-        }
-        // Fall through to use original quota response
-
-        let is_unlimited = quota.remaining == -1;
-        self.redis
-            .set_quota(
-                slug,
-                quota.remaining,
-                quota.limit,
-                quota.period_end.unwrap_or(0),
-                is_unlimited,
-                user_id,
-            )
-            .await;
-
-        Ok(())
+
+            let user_id = quota.user_id.as_deref().unwrap_or("");
+
+            // Handle free users needing period start
+            if quota.needs_period_start
+                && !user_id.is_empty()
+                && let Ok(period) = self.call_check_period(user_id).await
+            {
+                if period.error.is_empty() {
+                    self.redis
+                        .set_quota(
+                            slug,
+                            period.remaining,
+                            period.limit,
+                            period.period_end.unwrap_or(0),
+                            false,
+                            user_id,
+                        )
+                        .await;
+                    return Ok(());
+                } else if period.error == "quota_exceeded" {
+                    self.redis
+                        .set_quota(
+                            slug,
+                            0,
+                            period.limit,
+                            period.period_end.unwrap_or(0),
+                            false,
+                            user_id,
+                        )
+                        .await;
+                    return Ok(());
+                }
+            }
+            // Fall through to use original quota response
+
+            let is_unlimited = quota.remaining == -1;
+            self.redis
+                .set_quota(
+                    slug,
+                    quota.remaining,
+                    quota.limit,
+                    quota.period_end.unwrap_or(0),
+                    is_unlimited,
+                    user_id,
+                )
+                .await;
+
+            Ok(())
+        })
+        .await
     }
 
     /// Call check-period to start a free user's billing period.
     async fn call_check_period(&self, user_id: &str) -> Result<CheckPeriodResponse, ConvexError> {
-        if !self.circuit.allow_request().await {
+        if !self.circuit.allow_request() {
             return Err(ConvexError::CircuitOpen);
         }
 
@@ -257,97 +342,141 @@ impl ConvexClient {
         cursor: Option<&str>,
         limit: u32,
     ) -> Result<UsersByPlanResponse, ConvexError> {
-        if !self.circuit.allow_request().await {
-            return Err(ConvexError::CircuitOpen);
-        }
-
-        let mut request = self
-            .http
-            .get(format!("{}/users-by-plan", self.base_url))
-            .query(&[("plan", plan), ("limit", &limit.to_string())])
-            .header("Authorization", format!("Bearer {}", self.secret));
-
-        if let Some(cursor) = cursor {
-            request = request.query(&[("cursor", cursor)]);
-        }
-
-        let resp = request.send().await.map_err(|e| {
-            self.record_failure_sync();
-            ConvexError::Network(e.to_string())
-        })?;
-
-        let (status, body) = self.read_body(resp).await?;
-
-        if status >= 500 {
-            self.record_failure_sync();
-            return Err(ConvexError::ServerError(status, body));
-        }
+        self.instrumented("list_users_by_plan", async {
+            let limit_str = limit.to_string();
+            let mut query = vec![("plan", plan), ("limit", limit_str.as_str())];
+            if let Some(cursor) = cursor {
+                query.push(("cursor", cursor));
+            }
 
-        self.record_success_sync();
+            let (status, body) = self
+                .get_with_retry(|| self.do_get("/users-by-plan", &query))
+                .await?;
 
-        if !(200..300).contains(&status) {
-            return Err(ConvexError::ClientError(status, body));
-        }
+            if !(200..300).contains(&status) {
+                return Err(ConvexError::ClientError(status, body));
+            }
 
-        serde_json::from_str(&body).map_err(|e| ConvexError::ParseError(e.to_string()))
+            serde_json::from_str(&body).map_err(|e| ConvexError::ParseError(e.to_string()))
+        })
+        .await
     }
 
-    /// Send a batch of captured requests to Convex.
+    /// Send a batch of captured requests to Convex. Applies the endpoint's
+    /// redaction rules (if any) to every request exactly once, right here,
+    /// just before the batch is serialized — regardless of how long a
+    /// request sat in the Redis buffer before this flush.
     pub async fn capture_batch(
         &self,
         slug: &str,
-        requests: Vec<BufferedRequest>,
+        mut requests: Vec<BufferedRequest>,
     ) -> Result<CaptureResponse, ConvexError> {
-        if !self.circuit.allow_request().await {
-            return Err(ConvexError::CircuitOpen);
-        }
+        self.instrumented("capture_batch", async {
+            if !self.circuit.allow_request() {
+                return Err(ConvexError::CircuitOpen);
+            }
 
-        let url = format!("{}/capture-batch", self.base_url);
-        let payload = BatchPayload {
-            slug: slug.to_string(),
-            requests,
-        };
+            if let Some(info) = self.redis.get_endpoint(slug).await
+                && let Some(raw) = info.redact_rules.as_deref().filter(|r| !r.is_empty())
+            {
+                let rules = self.redact.get_or_parse(slug, raw);
+                if !rules.is_empty() {
+                    for req in requests.iter_mut() {
+                        rules.apply(req);
+                    }
+                }
+            }
 
-        let resp = self
-            .http
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.secret))
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| {
+            let url = format!("{}/capture-batch", self.base_url);
+            let batch_id = compute_batch_id(slug, &requests);
+            let payload = BatchPayload {
+                slug: slug.to_string(),
+                requests,
+                batch_id,
+            };
+
+            let resp = self
+                .http
+                .post(&url)
+                .header("Authorization", format!("Bearer {}", self.secret))
+                .json(&payload)
+                .send()
+                .await
+                .map_err(|e| {
+                    self.record_failure_sync();
+                    ConvexError::Network(e.to_string())
+                })?;
+
+            let (status, body) = self.read_body(resp).await?;
+
+            if status >= 500 {
                 self.record_failure_sync();
-                ConvexError::Network(e.to_string())
-            })?;
-
-        let (status, body) = self.read_body(resp).await?;
-
-        if status >= 500 {
-            self.record_failure_sync();
-            return Err(ConvexError::ServerError(status, body));
-        }
+                return Err(ConvexError::ServerError(status, body));
+            }
 
-        self.record_success_sync();
+            self.record_success_sync();
 
-        if !(200..300).contains(&status) {
-            return Err(ConvexError::ClientError(status, body));
-        }
+            if !(200..300).contains(&status) {
+                return Err(ConvexError::ClientError(status, body));
+            }
 
-        serde_json::from_str(&body).map_err(|e| ConvexError::ParseError(e.to_string()))
+            serde_json::from_str(&body).map_err(|e| ConvexError::ParseError(e.to_string()))
+        })
+        .await
     }
 
-    // Spawn fire-and-forget circuit breaker updates on the tokio runtime.
+    // Pure in-process counter bumps (see `CircuitBreaker::record_failure`/
+    // `record_success`) — no spawn, no Redis round trip per call. A
+    // background task on the breaker flushes aggregated deltas to Redis on
+    // an interval, so we just mirror the freshly-cached state into the gauge.
     fn record_failure_sync(&self) {
-        let circuit = self.circuit.clone();
-        tokio::spawn(async move { circuit.record_failure().await });
+        self.circuit.record_failure();
+        crate::metrics::set_circuit_state(self.circuit.state());
     }
 
     fn record_success_sync(&self) {
-        let circuit = self.circuit.clone();
-        tokio::spawn(async move { circuit.record_success().await });
+        self.circuit.record_success();
+        crate::metrics::set_circuit_state(self.circuit.state());
     }
 }
 
+/// AWS-style decorrelated jitter: `min(cap, random_between(base, prev * 3))`.
+/// Grows like exponential backoff but avoids the thundering-herd effect of
+/// fixed exponential backoff, since each retrying caller's next delay depends
+/// on its own previous delay rather than a shared attempt counter.
+fn next_decorrelated_delay(prev: Duration, base: Duration, cap: Duration) -> Duration {
+    let base_ms = base.as_millis() as u64;
+    let cap_ms = cap.as_millis() as u64;
+    let upper_ms = (prev.as_millis() as u64)
+        .saturating_mul(3)
+        .max(base_ms)
+        .min(cap_ms);
+    let span = upper_ms.saturating_sub(base_ms);
+    let jittered_ms = base_ms + random_u64() % (span + 1);
+    Duration::from_millis(jittered_ms.min(cap_ms))
+}
+
+/// Hash-based pseudo-random u64 — avoids pulling in a `rand` dependency for a
+/// single jittered sleep calculation (same hash-based RNG approach used by
+/// `workers::flush`'s shuffle).
+fn random_u64() -> u64 {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::time::SystemTime;
+
+    static JITTER_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let seed = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+        ^ JITTER_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    hasher.finish()
+}
+
 #[derive(Debug)]
 pub enum ConvexError {
     CircuitOpen,
@@ -0,0 +1,3 @@
+pub mod circuit_breaker;
+pub mod client;
+pub mod types;
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -30,6 +31,19 @@ pub struct EndpointInfo {
     pub is_ephemeral: bool,
     pub expires_at: Option<i64>,
     pub mock_response: Option<MockResponse>,
+    /// Raw accept/reject rule text (see `crate::filter`), parsed and cached
+    /// in-process by slug. `None`/empty means accept everything.
+    #[serde(default)]
+    pub filter_rules: Option<String>,
+    /// Raw redaction rule text (see `crate::redact`), parsed and cached
+    /// in-process by slug. `None`/empty means no redaction.
+    #[serde(default)]
+    pub redact_rules: Option<String>,
+    /// Browser origins allowed to call this endpoint cross-origin. `None`/empty
+    /// means the endpoint hasn't opted into an allowlist, so every origin is
+    /// allowed (`Access-Control-Allow-Origin: *`) instead of a restricted set.
+    #[serde(default)]
+    pub allowed_origins: Option<Vec<String>>,
     #[serde(default)]
     pub error: String,
 }
@@ -96,6 +110,72 @@ pub struct BufferedRequest {
     pub query_params: HashMap<String, String>,
     pub ip: String,
     pub received_at: i64,
+    /// Number of times this request has been through `RedisState::requeue`
+    /// after a failed flush. Omitted from the wire payload while zero so a
+    /// first-attempt request looks exactly like it did before this field
+    /// existed.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub attempts: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+impl BufferedRequest {
+    /// Per-request fingerprint folded into the batch's Merkle root by
+    /// `compute_batch_id`. Mirrors the fields `RedisState::check_dedup`
+    /// hashes (minus slug and client IP header casing quirks, which don't
+    /// apply once the request is already buffered), plus `received_at`, so
+    /// the same buffered request fingerprints identically regardless of how
+    /// the batch was assembled.
+    fn fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.method.as_bytes());
+        hasher.update(b"|");
+        hasher.update(self.path.as_bytes());
+        hasher.update(b"|");
+        let body_bytes = self.body.as_bytes();
+        hasher.update(&body_bytes[..body_bytes.len().min(512)]);
+        hasher.update(b"|");
+        hasher.update(self.ip.as_bytes());
+        hasher.update(b"|");
+        hasher.update(self.received_at.to_le_bytes());
+        hasher.finalize().into()
+    }
+}
+
+/// Deterministic batch identity: a Merkle root over each request's
+/// fingerprint, namespaced by slug. Computing the same batch (same requests,
+/// same order) twice yields the same `batch_id`, so re-sending a batch after
+/// an ambiguous error (timeout, 5xx) lets Convex recognize the repeat and
+/// treat it as a no-op instead of double-inserting — see
+/// `ConvexClient::capture_batch` and `workers::flush::drain_slug`.
+pub fn compute_batch_id(slug: &str, requests: &[BufferedRequest]) -> String {
+    let mut level: Vec<[u8; 32]> = requests.iter().map(BufferedRequest::fingerprint).collect();
+    if level.is_empty() {
+        level.push(Sha256::digest(slug.as_bytes()).into());
+    }
+
+    while level.len() > 1 {
+        let mut next = Vec::with_capacity(level.len().div_ceil(2));
+        for pair in level.chunks(2) {
+            let mut hasher = Sha256::new();
+            hasher.update(pair[0]);
+            hasher.update(pair.get(1).unwrap_or(&pair[0]));
+            next.push(hasher.finalize().into());
+        }
+        level = next;
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(slug.as_bytes());
+    hasher.update(level[0]);
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -108,10 +188,18 @@ pub struct CaptureResponse {
     #[serde(default)]
     pub inserted: usize,
     pub mock_response: Option<MockResponse>,
+    /// Set when Convex recognized `BatchPayload::batch_id` as one it already
+    /// persisted and skipped re-inserting — lets the flush worker log a
+    /// retried-batch resolution distinctly from a fresh insert.
+    #[serde(default)]
+    pub already_committed: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BatchPayload {
     pub slug: String,
     pub requests: Vec<BufferedRequest>,
+    /// Deterministic identity for this exact batch (see `compute_batch_id`),
+    /// so Convex can dedup a retried batch server-side.
+    pub batch_id: String,
 }
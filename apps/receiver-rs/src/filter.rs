@@ -0,0 +1,698 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use axum::http::HeaderMap;
+
+/// Outcome of evaluating a per-endpoint `RuleSet` against an incoming webhook.
+/// `Transform` is reserved for future per-rule payload rewriting; today it's
+/// treated the same as `Accept` by the ingest path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    /// Carries the raw rule text that matched, for the rejection response and logs.
+    Reject(String),
+    Transform,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuleVerdict {
+    Accept,
+    Reject,
+    Transform,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Field {
+    Method,
+    Path,
+    Ip,
+    ContentType,
+    BodySize,
+    Header(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Str(String),
+    Num(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Field, CompareOp, Literal),
+    In(Field, Vec<String>),
+    NotIn(Field, Vec<String>),
+    /// IPv4 CIDR membership: (network, prefix_len).
+    Cidr(Field, u32, u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    verdict: RuleVerdict,
+    expr: Expr,
+    raw: String,
+}
+
+/// A parsed set of per-endpoint accept/reject rules, evaluated in order —
+/// the first rule whose expression matches determines the verdict. No rule
+/// matching defaults to `Accept`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+/// What the rule engine needs from an in-flight webhook request. Fields are
+/// borrowed, so building one copies nothing; each string comparison or set
+/// lookup still copies the field value it reads (see `field_str`).
+pub struct FilterInput<'a> {
+    pub method: &'a str,
+    pub path: &'a str,
+    pub ip: &'a str,
+    pub content_type: &'a str,
+    pub headers: &'a HeaderMap,
+    pub body_size: usize,
+}
+
+impl RuleSet {
+    /// Parse `;`-separated `accept|reject|transform if <expr>` rules.
+    /// Never used directly in the request path — go through `FilterCache`,
+    /// which falls back to an empty (accept-all) `RuleSet` on parse failure.
+    pub fn parse(src: &str) -> Result<Self, FilterParseError> {
+        let mut rules = Vec::new();
+        for clause in src.split(';') {
+            let clause = clause.trim();
+            if clause.is_empty() {
+                continue;
+            }
+            rules.push(parse_rule(clause)?);
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn evaluate(&self, input: &FilterInput) -> Verdict {
+        for rule in &self.rules {
+            if eval_expr(&rule.expr, input) {
+                return match rule.verdict {
+                    RuleVerdict::Accept => Verdict::Accept,
+                    RuleVerdict::Reject => Verdict::Reject(rule.raw.clone()),
+                    RuleVerdict::Transform => Verdict::Transform,
+                };
+            }
+        }
+        Verdict::Accept
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterParseError(String);
+
+impl std::fmt::Display for FilterParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "filter rule parse error: {}", self.0)
+    }
+}
+
+/// Parse-once-per-distinct-rule-text cache, keyed by slug. Shared via
+/// `Arc`/`RwLock` so cloning `FilterCache` (it lives on `AppState`) is cheap.
+/// A slug's entry is reparsed automatically whenever the cached `EndpointInfo`
+/// carries different rule text (e.g. after `/internal/cache-invalidate`).
+#[derive(Clone, Default)]
+pub struct FilterCache {
+    inner: Arc<RwLock<HashMap<String, (String, Arc<RuleSet>)>>>,
+}
+
+impl FilterCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_or_parse(&self, slug: &str, raw: &str) -> Arc<RuleSet> {
+        if let Ok(cache) = self.inner.read()
+            && let Some((cached_raw, parsed)) = cache.get(slug)
+            && cached_raw == raw
+        {
+            return parsed.clone();
+        }
+
+        let parsed = Arc::new(RuleSet::parse(raw).unwrap_or_else(|e| {
+            tracing::warn!(slug, rules = raw, error = %e, "failed to parse filter rules, defaulting to accept-all");
+            RuleSet::default()
+        }));
+
+        if let Ok(mut cache) = self.inner.write() {
+            cache.insert(slug.to_string(), (raw.to_string(), parsed.clone()));
+        }
+
+        parsed
+    }
+}
+
+// --- Evaluation -------------------------------------------------------
+
+fn eval_expr(expr: &Expr, input: &FilterInput) -> bool {
+    match expr {
+        Expr::Not(inner) => !eval_expr(inner, input),
+        Expr::And(a, b) => eval_expr(a, input) && eval_expr(b, input),
+        Expr::Or(a, b) => eval_expr(a, input) || eval_expr(b, input),
+        Expr::Compare(field, op, lit) => eval_compare(field, *op, lit, input),
+        Expr::In(field, values) => {
+            let actual = field_str(field, input);
+            values.iter().any(|v| v.eq_ignore_ascii_case(&actual))
+        }
+        Expr::NotIn(field, values) => {
+            let actual = field_str(field, input);
+            !values.iter().any(|v| v.eq_ignore_ascii_case(&actual))
+        }
+        Expr::Cidr(field, network, prefix_len) => {
+            if *field != Field::Ip {
+                return false;
+            }
+            match parse_ipv4(input.ip) {
+                Some(ip) => ip_in_cidr(ip, *network, *prefix_len),
+                None => false,
+            }
+        }
+    }
+}
+
+fn field_str(field: &Field, input: &FilterInput) -> String {
+    match field {
+        Field::Method => input.method.to_string(),
+        Field::Path => input.path.to_string(),
+        Field::Ip => input.ip.to_string(),
+        Field::ContentType => input.content_type.to_string(),
+        Field::BodySize => input.body_size.to_string(),
+        Field::Header(name) => input
+            .headers
+            .get(name.as_str())
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+fn eval_compare(field: &Field, op: CompareOp, lit: &Literal, input: &FilterInput) -> bool {
+    if *field == Field::BodySize {
+        let actual = input.body_size as f64;
+        let Literal::Num(expected) = lit else {
+            return false;
+        };
+        return match op {
+            CompareOp::Eq => actual == *expected,
+            CompareOp::Ne => actual != *expected,
+            CompareOp::Gt => actual > *expected,
+            CompareOp::Lt => actual < *expected,
+            CompareOp::Ge => actual >= *expected,
+            CompareOp::Le => actual <= *expected,
+        };
+    }
+
+    let Literal::Str(expected) = lit else {
+        return false;
+    };
+    let actual = field_str(field, input);
+    match op {
+        CompareOp::Eq => actual.eq_ignore_ascii_case(expected),
+        CompareOp::Ne => !actual.eq_ignore_ascii_case(expected),
+        // Ordering comparisons only make sense for body_size; treat as
+        // always-false rather than panic on a malformed rule.
+        _ => false,
+    }
+}
+
+fn parse_ipv4(s: &str) -> Option<u32> {
+    let mut octets = [0u8; 4];
+    let mut parts = s.split('.');
+    for octet in &mut octets {
+        *octet = parts.next()?.parse().ok()?;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(u32::from_be_bytes(octets))
+}
+
+fn ip_in_cidr(ip: u32, network: u32, prefix_len: u8) -> bool {
+    if prefix_len == 0 {
+        return true;
+    }
+    if prefix_len > 32 {
+        return false;
+    }
+    let mask = u32::MAX.checked_shl(32 - u32::from(prefix_len)).unwrap_or(0);
+    (ip & mask) == (network & mask)
+}
+
+fn parse_ipv4_cidr(s: &str) -> Option<(u32, u8)> {
+    let (addr, prefix) = s.split_once('/')?;
+    let network = parse_ipv4(addr)?;
+    let prefix_len: u8 = prefix.parse().ok()?;
+    if prefix_len > 32 {
+        return None;
+    }
+    Some((network, prefix_len))
+}
+
+// --- Parsing ------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Comma,
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, FilterParseError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = src.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        match c {
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            '{' => {
+                tokens.push(Token::LBrace);
+                i += 1;
+            }
+            '}' => {
+                tokens.push(Token::RBrace);
+                i += 1;
+            }
+            '[' => {
+                tokens.push(Token::LBracket);
+                i += 1;
+            }
+            ']' => {
+                tokens.push(Token::RBracket);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '!' if chars.get(i + 1) == Some(&'=') => {
+                tokens.push(Token::Ne);
+                i += 2;
+            }
+            '>' if chars.get(i + 1) == Some(&'=') => {
+                tokens.push(Token::Ge);
+                i += 2;
+            }
+            '<' if chars.get(i + 1) == Some(&'=') => {
+                tokens.push(Token::Le);
+                i += 2;
+            }
+            '>' => {
+                tokens.push(Token::Gt);
+                i += 1;
+            }
+            '<' => {
+                tokens.push(Token::Lt);
+                i += 1;
+            }
+            '=' => {
+                tokens.push(Token::Eq);
+                i += 1;
+            }
+            '"' => {
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(FilterParseError("unterminated string".into())),
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some('\\') if chars.get(i + 1) == Some(&'"') => {
+                            s.push('"');
+                            i += 2;
+                        }
+                        Some(c) => {
+                            s.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            c if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(char::is_ascii_digit)) => {
+                let start = i;
+                i += 1;
+                while chars.get(i).is_some_and(|c| c.is_ascii_digit() || *c == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let n = text
+                    .parse::<f64>()
+                    .map_err(|_| FilterParseError(format!("invalid number '{text}'")))?;
+                tokens.push(Token::Num(n));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '-' => {
+                let start = i;
+                while chars
+                    .get(i)
+                    .is_some_and(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+                {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                tokens.push(Token::Ident(text));
+            }
+            other => return Err(FilterParseError(format!("unexpected character '{other}'"))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn expect_ident(&mut self, want: &str) -> Result<(), FilterParseError> {
+        match self.next() {
+            Some(Token::Ident(ref s)) if s.eq_ignore_ascii_case(want) => Ok(()),
+            other => Err(FilterParseError(format!("expected '{want}', found {other:?}"))),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, FilterParseError> {
+        self.parse_or()
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, FilterParseError> {
+        let mut left = self.parse_and()?;
+        while let Some(Token::Ident(s)) = self.peek()
+            && s.eq_ignore_ascii_case("or")
+        {
+            self.next();
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, FilterParseError> {
+        let mut left = self.parse_unary()?;
+        while let Some(Token::Ident(s)) = self.peek()
+            && s.eq_ignore_ascii_case("and")
+        {
+            self.next();
+            let right = self.parse_unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, FilterParseError> {
+        if let Some(Token::Ident(s)) = self.peek()
+            && s.eq_ignore_ascii_case("not")
+        {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, FilterParseError> {
+        if matches!(self.peek(), Some(Token::LParen)) {
+            self.next();
+            let inner = self.parse_expr()?;
+            match self.next() {
+                Some(Token::RParen) => Ok(inner),
+                other => Err(FilterParseError(format!("expected ')', found {other:?}"))),
+            }
+        } else {
+            self.parse_comparison()
+        }
+    }
+
+    fn parse_field(&mut self) -> Result<Field, FilterParseError> {
+        match self.next() {
+            Some(Token::Ident(name)) => match name.to_ascii_lowercase().as_str() {
+                "method" => Ok(Field::Method),
+                "path" => Ok(Field::Path),
+                "ip" => Ok(Field::Ip),
+                "content_type" | "content-type" => Ok(Field::ContentType),
+                "body_size" => Ok(Field::BodySize),
+                "header" => {
+                    match self.next() {
+                        Some(Token::LBracket) => {}
+                        other => return Err(FilterParseError(format!("expected '[', found {other:?}"))),
+                    }
+                    let header_name = match self.next() {
+                        Some(Token::Str(s)) => s,
+                        other => return Err(FilterParseError(format!("expected header name, found {other:?}"))),
+                    };
+                    match self.next() {
+                        Some(Token::RBracket) => {}
+                        other => return Err(FilterParseError(format!("expected ']', found {other:?}"))),
+                    }
+                    Ok(Field::Header(header_name))
+                }
+                other => Err(FilterParseError(format!("unknown field '{other}'"))),
+            },
+            other => Err(FilterParseError(format!("expected field name, found {other:?}"))),
+        }
+    }
+
+    fn parse_string_set(&mut self) -> Result<Vec<String>, FilterParseError> {
+        match self.next() {
+            Some(Token::LBrace) => {}
+            other => return Err(FilterParseError(format!("expected '{{', found {other:?}"))),
+        }
+        let mut values = Vec::new();
+        loop {
+            match self.next() {
+                Some(Token::Str(s)) => values.push(s),
+                other => return Err(FilterParseError(format!("expected string, found {other:?}"))),
+            }
+            match self.peek() {
+                Some(Token::Comma) => {
+                    self.next();
+                }
+                Some(Token::RBrace) => {
+                    self.next();
+                    break;
+                }
+                other => return Err(FilterParseError(format!("expected ',' or '}}', found {other:?}"))),
+            }
+        }
+        Ok(values)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, FilterParseError> {
+        let field = self.parse_field()?;
+        match self.next() {
+            Some(Token::Eq) => {
+                let lit = self.parse_literal()?;
+                Ok(Expr::Compare(field, CompareOp::Eq, lit))
+            }
+            Some(Token::Ne) => {
+                let lit = self.parse_literal()?;
+                Ok(Expr::Compare(field, CompareOp::Ne, lit))
+            }
+            Some(Token::Gt) => Ok(Expr::Compare(field, CompareOp::Gt, self.parse_literal()?)),
+            Some(Token::Lt) => Ok(Expr::Compare(field, CompareOp::Lt, self.parse_literal()?)),
+            Some(Token::Ge) => Ok(Expr::Compare(field, CompareOp::Ge, self.parse_literal()?)),
+            Some(Token::Le) => Ok(Expr::Compare(field, CompareOp::Le, self.parse_literal()?)),
+            Some(Token::Ident(op)) if op.eq_ignore_ascii_case("in") => {
+                Ok(Expr::In(field, self.parse_string_set()?))
+            }
+            Some(Token::Ident(op)) if op.eq_ignore_ascii_case("not_in") => {
+                Ok(Expr::NotIn(field, self.parse_string_set()?))
+            }
+            Some(Token::Ident(op)) if op.eq_ignore_ascii_case("cidr") => match self.next() {
+                Some(Token::Str(s)) => {
+                    let (network, prefix_len) = parse_ipv4_cidr(&s)
+                        .ok_or_else(|| FilterParseError(format!("invalid CIDR literal '{s}'")))?;
+                    Ok(Expr::Cidr(field, network, prefix_len))
+                }
+                other => Err(FilterParseError(format!("expected CIDR string, found {other:?}"))),
+            },
+            other => Err(FilterParseError(format!("expected operator, found {other:?}"))),
+        }
+    }
+
+    fn parse_literal(&mut self) -> Result<Literal, FilterParseError> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(Literal::Str(s)),
+            Some(Token::Num(n)) => Ok(Literal::Num(n)),
+            other => Err(FilterParseError(format!("expected value, found {other:?}"))),
+        }
+    }
+}
+
+fn parse_rule(clause: &str) -> Result<Rule, FilterParseError> {
+    let tokens = tokenize(clause)?;
+    let mut parser = Parser { tokens, pos: 0 };
+
+    let verdict = match parser.next() {
+        Some(Token::Ident(ref s)) if s.eq_ignore_ascii_case("accept") => RuleVerdict::Accept,
+        Some(Token::Ident(ref s)) if s.eq_ignore_ascii_case("reject") => RuleVerdict::Reject,
+        Some(Token::Ident(ref s)) if s.eq_ignore_ascii_case("transform") => RuleVerdict::Transform,
+        other => {
+            return Err(FilterParseError(format!(
+                "expected 'accept', 'reject', or 'transform', found {other:?}"
+            )));
+        }
+    };
+
+    parser.expect_ident("if")?;
+    let expr = parser.parse_expr()?;
+
+    if parser.pos != parser.tokens.len() {
+        return Err(FilterParseError("trailing tokens after expression".into()));
+    }
+
+    Ok(Rule {
+        verdict,
+        expr,
+        raw: clause.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input<'a>(headers: &'a HeaderMap) -> FilterInput<'a> {
+        FilterInput {
+            method: "POST",
+            path: "/orders",
+            ip: "10.1.2.3",
+            content_type: "application/json",
+            headers,
+            body_size: 42,
+        }
+    }
+
+    #[test]
+    fn accept_all_by_default() {
+        let rules = RuleSet::parse("").unwrap();
+        let headers = HeaderMap::new();
+        assert_eq!(rules.evaluate(&input(&headers)), Verdict::Accept);
+    }
+
+    #[test]
+    fn rejects_on_content_type_mismatch() {
+        let rules = RuleSet::parse(r#"reject if content_type != "application/json""#).unwrap();
+        let headers = HeaderMap::new();
+        let mut ctx = input(&headers);
+        ctx.content_type = "text/plain";
+        assert_eq!(
+            rules.evaluate(&ctx),
+            Verdict::Reject(r#"reject if content_type != "application/json""#.to_string())
+        );
+    }
+
+    #[test]
+    fn accepts_matching_content_type() {
+        let rules = RuleSet::parse(r#"reject if content_type != "application/json""#).unwrap();
+        let headers = HeaderMap::new();
+        assert_eq!(rules.evaluate(&input(&headers)), Verdict::Accept);
+    }
+
+    #[test]
+    fn header_in_set() {
+        let rules =
+            RuleSet::parse(r#"reject if not (header["X-Event"] in {"created", "updated"})"#)
+                .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-event", "deleted".parse().unwrap());
+        assert!(matches!(rules.evaluate(&input(&headers)), Verdict::Reject(_)));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-event", "created".parse().unwrap());
+        assert_eq!(rules.evaluate(&input(&headers)), Verdict::Accept);
+    }
+
+    #[test]
+    fn cidr_match() {
+        let rules = RuleSet::parse(r#"reject if ip cidr "10.0.0.0/8""#).unwrap();
+        let headers = HeaderMap::new();
+        assert!(matches!(rules.evaluate(&input(&headers)), Verdict::Reject(_)));
+
+        let rules = RuleSet::parse(r#"reject if ip cidr "192.168.0.0/16""#).unwrap();
+        assert_eq!(rules.evaluate(&input(&headers)), Verdict::Accept);
+    }
+
+    #[test]
+    fn body_size_threshold() {
+        let rules = RuleSet::parse("reject if body_size > 100").unwrap();
+        let headers = HeaderMap::new();
+        assert_eq!(rules.evaluate(&input(&headers)), Verdict::Accept);
+
+        let rules = RuleSet::parse("reject if body_size > 10").unwrap();
+        assert!(matches!(rules.evaluate(&input(&headers)), Verdict::Reject(_)));
+    }
+
+    #[test]
+    fn malformed_rule_falls_back_to_accept() {
+        let cache = FilterCache::new();
+        let ruleset = cache.get_or_parse("demo", "reject when (((");
+        let headers = HeaderMap::new();
+        assert_eq!(ruleset.evaluate(&input(&headers)), Verdict::Accept);
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = RuleSet::parse(
+            r#"accept if method = "POST"; reject if content_type != "application/json""#,
+        )
+        .unwrap();
+        let headers = HeaderMap::new();
+        let mut ctx = input(&headers);
+        ctx.content_type = "text/plain";
+        assert_eq!(rules.evaluate(&ctx), Verdict::Accept);
+    }
+}
@@ -0,0 +1,51 @@
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use std::sync::Arc;
+
+use crate::config::ConfigError;
+use crate::handlers::auth::verify_bearer_token;
+use crate::AppState;
+
+/// POST /admin/reload — re-reads the environment and hot-swaps the subset of
+/// `Config` that doesn't require a restart. Modeled on `cache_invalidate`:
+/// same shared-secret auth, same plain-JSON error shape. The `SIGHUP` handler
+/// in `main` triggers the identical `Config::reload()` path.
+pub async fn reload_config(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let auth = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if !verify_bearer_token(auth, &state.config.load().capture_shared_secret) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            axum::Json(serde_json::json!({"error": "unauthorized"})),
+        );
+    }
+
+    match state.config.load().reload() {
+        Ok(new_config) => {
+            state.config.store(Arc::new(new_config));
+            tracing::info!("config reloaded via admin endpoint");
+            (StatusCode::OK, axum::Json(serde_json::json!({"ok": true})))
+        }
+        Err(ConfigError::ColdFieldsChanged(fields)) => {
+            tracing::warn!(?fields, "config reload rejected: restart-only fields changed");
+            (
+                StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({
+                    "error": "restart_required",
+                    "changed_fields": fields,
+                })),
+            )
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "config reload failed validation");
+            (
+                StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({"error": e.to_string()})),
+            )
+        }
+    }
+}
@@ -26,7 +26,7 @@ pub async fn cache_invalidate(
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
 
-    let expected = format!("Bearer {}", state.config.capture_shared_secret);
+    let expected = format!("Bearer {}", state.config.load().capture_shared_secret);
 
     // Hash both to fixed 8 bytes so ct_eq doesn't short-circuit on length difference.
     let auth_hash = hash_to_fixed(auth.as_bytes());
@@ -46,10 +46,11 @@ pub async fn cache_invalidate(
         );
     }
 
-    // Evict both endpoint and quota caches
+    // Evict endpoint, quota, and cached search results
     state.redis.evict_endpoint(&slug).await;
     state.redis.evict_quota(&slug).await;
-    tracing::debug!(slug, "cache invalidated (endpoint + quota)");
+    state.redis.evict_search_cache_for_slug(&slug).await;
+    tracing::debug!(slug, "cache invalidated (endpoint + quota + search)");
 
     (
         StatusCode::OK,
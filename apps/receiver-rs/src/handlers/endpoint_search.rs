@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+
+use crate::AppState;
+use crate::clickhouse::query::{
+    PageCursor, RequestSearchError, RequestSearchParams, build_request_count_sql,
+    build_request_page_sql, build_request_search_sql,
+};
+use crate::handlers::auth::verify_bearer_token;
+use crate::time::TimestampFormat;
+
+/// `GET /endpoints/:slug/requests/search` — rich query surface over a single
+/// endpoint's captured requests. Pass `?count=true` to get `{"count": N}`
+/// instead of rows (same filters, no pagination). Pass `?received_at=rfc3339`
+/// to render `receivedAt` as an RFC3339 string instead of the default raw
+/// epoch-ms number (see `crate::time::TimestampFormat`). Pass `?cursor=` (or
+/// a previous response's `nextCursor`) to page through a large history via
+/// keyset pagination instead of `offset`/`limit` — see `RequestSearchParams::cursor`.
+pub async fn search_endpoint_requests(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+    Query(params): Query<RequestSearchParams>,
+    Query(mode): Query<CountMode>,
+    Query(output): Query<OutputFormat>,
+) -> impl IntoResponse {
+    let auth = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if !verify_bearer_token(auth, &state.config.load().capture_shared_secret) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            axum::Json(serde_json::json!({"error": "unauthorized"})),
+        );
+    }
+
+    let clickhouse = match &state.clickhouse {
+        Some(ch) => ch,
+        None => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                axum::Json(serde_json::json!({"error": "search not available"})),
+            );
+        }
+    };
+
+    let db = state.config.load().clickhouse_database.clone();
+
+    if mode.count {
+        let sql = match build_request_count_sql(&slug, &db, &params) {
+            Ok(sql) => sql,
+            Err(e) => return bad_request(e),
+        };
+
+        return match tokio::time::timeout(Duration::from_secs(5), clickhouse.query_count(&sql))
+            .await
+        {
+            Ok(Ok(count)) => (StatusCode::OK, axum::Json(serde_json::json!({"count": count}))),
+            Ok(Err(e)) => {
+                tracing::error!(error = %e, "ClickHouse count query failed");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    axum::Json(serde_json::json!({"error": "count query failed"})),
+                )
+            }
+            Err(_) => {
+                tracing::error!("ClickHouse count query timed out");
+                (
+                    StatusCode::GATEWAY_TIMEOUT,
+                    axum::Json(serde_json::json!({"error": "count query timed out"})),
+                )
+            }
+        };
+    }
+
+    let format: TimestampFormat = output
+        .received_at
+        .as_deref()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_default();
+
+    if let Some(cursor_param) = &params.cursor {
+        let cursor = if cursor_param.is_empty() {
+            None
+        } else {
+            match PageCursor::decode(cursor_param) {
+                Some(cursor) => Some(cursor),
+                None => return bad_request(RequestSearchError::InvalidCursor),
+            }
+        };
+
+        let limit = params.limit.unwrap_or(50).min(200);
+        let sql = match build_request_page_sql(&slug, &db, &params, cursor, limit) {
+            Ok(sql) => sql,
+            Err(e) => return bad_request(e),
+        };
+
+        return match tokio::time::timeout(
+            Duration::from_secs(5),
+            clickhouse.query_page(&sql, limit as usize),
+        )
+        .await
+        {
+            Ok(Ok((rows, next_cursor))) => {
+                let rendered: Vec<_> = rows.iter().map(|r| r.to_json(format)).collect();
+                (
+                    StatusCode::OK,
+                    axum::Json(serde_json::json!({"requests": rendered, "nextCursor": next_cursor})),
+                )
+            }
+            Ok(Err(e)) => {
+                tracing::error!(error = %e, "ClickHouse paginated search query failed");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    axum::Json(serde_json::json!({"error": "search query failed"})),
+                )
+            }
+            Err(_) => {
+                tracing::error!("ClickHouse paginated search query timed out");
+                (
+                    StatusCode::GATEWAY_TIMEOUT,
+                    axum::Json(serde_json::json!({"error": "search query timed out"})),
+                )
+            }
+        };
+    }
+
+    let sql = match build_request_search_sql(&slug, &db, &params) {
+        Ok(sql) => sql,
+        Err(e) => return bad_request(e),
+    };
+
+    match tokio::time::timeout(Duration::from_secs(5), clickhouse.query_requests(&sql)).await {
+        Ok(Ok(results)) => {
+            let rendered: Vec<_> = results.iter().map(|r| r.to_json(format)).collect();
+            (
+                StatusCode::OK,
+                axum::Json(serde_json::json!({"requests": rendered})),
+            )
+        }
+        Ok(Err(e)) => {
+            tracing::error!(error = %e, "ClickHouse search query failed");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({"error": "search query failed"})),
+            )
+        }
+        Err(_) => {
+            tracing::error!("ClickHouse search query timed out");
+            (
+                StatusCode::GATEWAY_TIMEOUT,
+                axum::Json(serde_json::json!({"error": "search query timed out"})),
+            )
+        }
+    }
+}
+
+fn bad_request(err: RequestSearchError) -> (StatusCode, axum::Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        axum::Json(serde_json::json!({"error": err.to_string()})),
+    )
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CountMode {
+    #[serde(default)]
+    count: bool,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct OutputFormat {
+    received_at: Option<String>,
+}
@@ -0,0 +1,244 @@
+use std::time::Duration;
+
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+use crate::handlers::auth::verify_bearer_token;
+use crate::storage::{SearchQuery, SearchQueryError, build_where_clause};
+
+/// Histogram bucket width when the caller doesn't specify one — one-minute
+/// buckets give a reasonable default resolution for a dashboard time range.
+const DEFAULT_BUCKET_SECS: u32 = 60;
+/// Cap on distinct values per facet — `slug`/`content_type` are effectively
+/// unbounded cardinality, so without a limit one noisy tenant could return
+/// thousands of rows for a single chart.
+const MAX_FACET_VALUES: u32 = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct FacetParams {
+    user_id: String,
+    plan: Option<String>,
+    slug: Option<String>,
+    method: Option<String>,
+    q: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+    /// Histogram bucket width, in seconds (default 60 = per-minute volume).
+    bucket_secs: Option<u32>,
+}
+
+impl From<&FacetParams> for SearchQuery {
+    fn from(params: &FacetParams) -> Self {
+        SearchQuery {
+            user_id: params.user_id.clone(),
+            plan: params.plan.clone(),
+            slug: params.slug.clone(),
+            method: params.method.clone(),
+            q: params.q.clone(),
+            from: params.from,
+            to: params.to,
+            // Facets group rows rather than paging through them.
+            limit: 0,
+            offset: 0,
+            order_desc: true,
+            after: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FacetCountRow {
+    value: String,
+    count: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FacetCount {
+    value: String,
+    count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistogramRow {
+    bucket: String,
+    count: String,
+}
+
+#[derive(Debug, Serialize)]
+struct HistogramBucket {
+    bucket: String,
+    count: u64,
+}
+
+fn into_counts(rows: Vec<FacetCountRow>) -> Vec<FacetCount> {
+    rows.into_iter()
+        .map(|row| FacetCount {
+            value: row.value,
+            count: row.count.parse().unwrap_or(0),
+        })
+        .collect()
+}
+
+/// `GET /search/facets` — aggregate counts over the same filters `search`
+/// accepts, instead of rows: request counts grouped by `method`, `slug` and
+/// `content_type`, plus a time-bucketed histogram of `received_at`. Lets a
+/// caller narrow a window and see volume at a glance ("POST: 412, GET: 30")
+/// without paging through results.
+pub async fn facets(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<FacetParams>,
+) -> impl IntoResponse {
+    let auth = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if !verify_bearer_token(auth, &state.config.load().capture_shared_secret) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            axum::Json(serde_json::json!({"error": "unauthorized"})),
+        );
+    }
+
+    if params.user_id.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({"error": "user_id is required"})),
+        );
+    }
+
+    let clickhouse = match &state.clickhouse {
+        Some(ch) => ch,
+        None => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                axum::Json(serde_json::json!({"error": "search not available"})),
+            );
+        }
+    };
+
+    let query: SearchQuery = (&params).into();
+
+    let where_clause = match build_where_clause(&query) {
+        Ok(clause) => clause,
+        Err(SearchQueryError::InvalidPlan) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({"error": "invalid plan"})),
+            );
+        }
+        Err(SearchQueryError::InvalidSlug) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({"error": "invalid slug"})),
+            );
+        }
+        Err(SearchQueryError::InvalidCursor) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({"error": "invalid cursor"})),
+            );
+        }
+    };
+
+    let db = crate::clickhouse::client::escape_clickhouse_identifier(&state.config.load().clickhouse_database);
+    let bucket_secs = params.bucket_secs.unwrap_or(DEFAULT_BUCKET_SECS).clamp(1, 86_400);
+
+    let method_sql = format!(
+        "SELECT method AS value, count() AS count FROM `{db}`.`requests` WHERE {where_clause} \
+         GROUP BY value ORDER BY count DESC LIMIT {MAX_FACET_VALUES}"
+    );
+    let slug_sql = format!(
+        "SELECT slug AS value, count() AS count FROM `{db}`.`requests` WHERE {where_clause} \
+         GROUP BY value ORDER BY count DESC LIMIT {MAX_FACET_VALUES}"
+    );
+    let content_type_sql = format!(
+        "SELECT content_type AS value, count() AS count FROM `{db}`.`requests` WHERE {where_clause} \
+         GROUP BY value ORDER BY count DESC LIMIT {MAX_FACET_VALUES}"
+    );
+    let histogram_sql = format!(
+        "SELECT toStartOfInterval(received_at, INTERVAL {bucket_secs} SECOND) AS bucket, count() AS count \
+         FROM `{db}`.`requests` WHERE {where_clause} GROUP BY bucket ORDER BY bucket ASC"
+    );
+
+    let queries = async {
+        tokio::try_join!(
+            clickhouse.query_json::<FacetCountRow>(&method_sql),
+            clickhouse.query_json::<FacetCountRow>(&slug_sql),
+            clickhouse.query_json::<FacetCountRow>(&content_type_sql),
+            clickhouse.query_json::<HistogramRow>(&histogram_sql),
+        )
+    };
+
+    match tokio::time::timeout(Duration::from_secs(5), queries).await {
+        Ok(Ok((methods, slugs, content_types, histogram))) => {
+            let histogram: Vec<HistogramBucket> = histogram
+                .into_iter()
+                .map(|row| HistogramBucket {
+                    bucket: row.bucket,
+                    count: row.count.parse().unwrap_or(0),
+                })
+                .collect();
+
+            (
+                StatusCode::OK,
+                axum::Json(serde_json::json!({
+                    "facets": {
+                        "method": into_counts(methods),
+                        "slug": into_counts(slugs),
+                        "contentType": into_counts(content_types),
+                    },
+                    "histogram": histogram,
+                })),
+            )
+        }
+        Ok(Err(e)) => {
+            tracing::error!(error = %e, "facets query failed");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({"error": "facets query failed"})),
+            )
+        }
+        Err(_) => {
+            tracing::error!("facets query timed out");
+            (
+                StatusCode::GATEWAY_TIMEOUT,
+                axum::Json(serde_json::json!({"error": "facets query timed out"})),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FacetParams, SearchQuery};
+
+    #[test]
+    fn facet_params_convert_into_search_query_without_paging() {
+        let params = FacetParams {
+            user_id: "user_123".to_string(),
+            plan: Some("free".to_string()),
+            slug: Some("demo_slug".to_string()),
+            method: Some("POST".to_string()),
+            q: None,
+            from: Some(1_000),
+            to: Some(2_000),
+            bucket_secs: Some(30),
+        };
+
+        let query: SearchQuery = (&params).into();
+
+        assert_eq!(query.user_id, "user_123");
+        assert_eq!(query.plan.as_deref(), Some("free"));
+        assert_eq!(query.slug.as_deref(), Some("demo_slug"));
+        assert_eq!(query.from, Some(1_000));
+        assert_eq!(query.to, Some(2_000));
+        assert_eq!(query.limit, 0);
+        assert_eq!(query.offset, 0);
+    }
+}
@@ -5,8 +5,31 @@ use axum::response::IntoResponse;
 use crate::AppState;
 
 pub async fn health(State(state): State<AppState>) -> impl IntoResponse {
-    let degraded = state.convex.circuit().is_degraded().await;
-    let circuit_state = state.convex.circuit().state().await;
+    // Draining takes priority over circuit state: once shutdown has started
+    // we want load balancers to stop routing here regardless of Convex health.
+    if *state.shutdown.borrow() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            axum::Json(serde_json::json!({"status": "draining"})),
+        );
+    }
+
+    let degraded = state.convex.circuit().is_degraded();
+    let circuit_state = state.convex.circuit().state();
+
+    let workers: Vec<_> = state
+        .workers
+        .snapshot()
+        .iter()
+        .map(|w| {
+            serde_json::json!({
+                "name": w.name(),
+                "alive": w.is_running(),
+                "restarts": w.restarts(),
+                "last_tick_ms": w.last_tick_ms(),
+            })
+        })
+        .collect();
 
     let status = if degraded {
         StatusCode::SERVICE_UNAVAILABLE
@@ -21,6 +44,7 @@ pub async fn health(State(state): State<AppState>) -> impl IntoResponse {
         axum::Json(serde_json::json!({
             "status": label,
             "circuit": circuit_state.to_string(),
+            "workers": workers,
         })),
     )
 }
@@ -0,0 +1,13 @@
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+
+/// Prometheus scrape endpoint. Unauthenticated and outside the internal
+/// bearer-auth group, like `/health` — scrapers aren't configured with this
+/// service's shared secret.
+pub async fn metrics() -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        crate::metrics::render(),
+    )
+}
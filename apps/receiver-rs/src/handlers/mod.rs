@@ -0,0 +1,10 @@
+pub mod admin;
+pub mod auth;
+pub mod cache_invalidate;
+pub mod endpoint_search;
+pub mod facets;
+pub mod health;
+pub mod metrics;
+pub mod search;
+pub mod stream;
+pub mod webhook;
@@ -2,14 +2,15 @@ use std::time::Duration;
 
 use axum::extract::{Query, State};
 use axum::http::{HeaderMap, StatusCode};
-use axum::response::IntoResponse;
+use axum::response::{IntoResponse, Response};
 
 use serde::Deserialize;
 
 use crate::AppState;
-use crate::clickhouse::client::{escape_clickhouse_identifier, escape_clickhouse_string};
+use crate::clickhouse::query::PageCursor;
 use crate::handlers::auth::verify_bearer_token;
-use crate::handlers::webhook::is_valid_slug;
+use crate::storage::{SearchError, SearchQuery, SearchQueryError};
+use crate::time::TimestampFormat;
 
 #[derive(Debug, Deserialize)]
 pub struct SearchParams {
@@ -23,166 +24,190 @@ pub struct SearchParams {
     limit: Option<u32>,
     offset: Option<u32>,
     order: Option<String>,
+    /// Output format for `received_at`: `epoch_ms` (default) or `rfc3339`.
+    received_at: Option<String>,
+    /// Opt into keyset pagination instead of `offset`: omit for the old
+    /// behavior, or pass a previous response's `nextCursor` to continue one.
+    /// Validated separately in `search` (an invalid token is a 400, whereas
+    /// `From<&SearchParams>` below is infallible), then applied to
+    /// `SearchQuery::after`.
+    after: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum SearchSqlError {
-    InvalidPlan,
-    InvalidSlug,
-}
-
-fn free_retention_clause_for_plan(
-    plan: Option<&str>,
-) -> Result<Option<&'static str>, SearchSqlError> {
-    match plan {
-        Some("free") => Ok(Some("received_at >= now() - INTERVAL 7 DAY")),
-        Some("pro") | None => Ok(None),
-        Some(_) => Err(SearchSqlError::InvalidPlan),
-    }
-}
-
-fn build_search_sql(params: &SearchParams, db: &str) -> Result<String, SearchSqlError> {
-    let limit = params.limit.unwrap_or(50).min(200);
-    let offset = params.offset.unwrap_or(0).min(10_000);
-    let order = match params.order.as_deref() {
-        Some("asc") => "ASC",
-        _ => "DESC",
-    };
-
-    // Build WHERE clauses
-    let mut conditions = vec![format!(
-        "user_id = '{}'",
-        escape_clickhouse_string(&params.user_id)
-    )];
-
-    match free_retention_clause_for_plan(params.plan.as_deref()) {
-        Ok(Some(clause)) => conditions.push(clause.to_string()),
-        Ok(None) => {}
-        Err(err) => return Err(err),
-    }
-
-    if let Some(slug) = &params.slug {
-        if !is_valid_slug(slug) {
-            return Err(SearchSqlError::InvalidSlug);
+impl From<&SearchParams> for SearchQuery {
+    fn from(params: &SearchParams) -> Self {
+        SearchQuery {
+            user_id: params.user_id.clone(),
+            plan: params.plan.clone(),
+            slug: params.slug.clone(),
+            method: params.method.clone(),
+            q: params.q.clone(),
+            from: params.from,
+            to: params.to,
+            limit: params.limit.unwrap_or(50).min(200),
+            offset: params.offset.unwrap_or(0).min(10_000),
+            order_desc: params.order.as_deref() != Some("asc"),
+            // Set by `search` after validating `params.after` separately —
+            // an invalid cursor needs its own 400, which an infallible
+            // `From` can't produce.
+            after: None,
         }
-        conditions.push(format!("slug = '{}'", escape_clickhouse_string(slug)));
     }
-
-    if let Some(method) = &params.method
-        && method != "ALL"
-    {
-        conditions.push(format!("method = '{}'", escape_clickhouse_string(method)));
-    }
-
-    // Use multiSearchAny() for substring search — it does exact substring
-    // matching (no wildcard/regex escaping needed) and is supported by
-    // ngrambf_v1 skip indexes for efficient filtering.
-    if let Some(q) = &params.q
-        && !q.is_empty()
-    {
-        let escaped = escape_clickhouse_string(q);
-        conditions.push(format!(
-            "(multiSearchAny(path, ['{escaped}']) OR multiSearchAny(body, ['{escaped}']) OR multiSearchAny(headers, ['{escaped}']))"
-        ));
-    }
-
-    // Use integer arithmetic for timestamps to avoid f64 precision loss
-    // and potential scientific notation formatting.
-    if let Some(from) = params.from {
-        let secs = from.div_euclid(1000);
-        let ms = from.rem_euclid(1000) as u64;
-        conditions.push(format!(
-            "received_at >= toDateTime64('{secs}.{ms:03}', 3, 'UTC')"
-        ));
-    }
-
-    if let Some(to) = params.to {
-        let secs = to.div_euclid(1000);
-        let ms = to.rem_euclid(1000) as u64;
-        conditions.push(format!(
-            "received_at <= toDateTime64('{secs}.{ms:03}', 3, 'UTC')"
-        ));
-    }
-
-    let where_clause = conditions.join(" AND ");
-    let db = escape_clickhouse_identifier(db);
-
-    Ok(format!(
-        "SELECT endpoint_id, slug, user_id, method, path, headers, body, query_params, ip, content_type, size, is_ephemeral, received_at \
-         FROM `{db}`.`requests` \
-         WHERE {where_clause} \
-         ORDER BY received_at {order} \
-         LIMIT {limit} OFFSET {offset}"
-    ))
 }
 
+/// `GET /search` — returns `{"requests": [...]}`, plus `"nextCursor"` when
+/// `?after=` (or a previous response's `nextCursor`) opts into keyset
+/// pagination instead of `limit`/`offset`.
 pub async fn search(
     State(state): State<AppState>,
     headers: HeaderMap,
     Query(params): Query<SearchParams>,
-) -> impl IntoResponse {
+) -> Response {
+    let start = std::time::Instant::now();
+    // Every return below goes through `finish` so the outcome counter and
+    // handler-latency histogram stay in sync no matter which branch the
+    // request takes — see `handlers::webhook::handle_webhook` for the same
+    // pattern.
+    let finish = |outcome: &'static str, resp: (StatusCode, axum::Json<serde_json::Value>)| -> Response {
+        crate::metrics::record_search_request(outcome, start.elapsed());
+        resp.into_response()
+    };
+
     // Verify shared secret
     let auth = headers
         .get("authorization")
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
 
-    if !verify_bearer_token(auth, &state.config.capture_shared_secret) {
-        return (
-            StatusCode::UNAUTHORIZED,
-            axum::Json(serde_json::json!({"error": "unauthorized"})),
+    if !verify_bearer_token(auth, &state.config.load().capture_shared_secret) {
+        return finish(
+            "unauthorized",
+            (
+                StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({"error": "unauthorized"})),
+            ),
         );
     }
 
     // user_id is required and must be non-empty
     if params.user_id.is_empty() {
-        return (
-            StatusCode::BAD_REQUEST,
-            axum::Json(serde_json::json!({"error": "user_id is required"})),
+        return finish(
+            "missing_user_id",
+            (
+                StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({"error": "user_id is required"})),
+            ),
         );
     }
 
-    // ClickHouse must be enabled
-    let clickhouse = match &state.clickhouse {
-        Some(ch) => ch,
+    // A storage backend must be configured
+    let storage = match &state.storage {
+        Some(s) => s,
         None => {
-            return (
-                StatusCode::SERVICE_UNAVAILABLE,
-                axum::Json(serde_json::json!({"error": "search not available"})),
+            return finish(
+                "unavailable",
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    axum::Json(serde_json::json!({"error": "search not available"})),
+                ),
             );
         }
     };
 
-    let sql = match build_search_sql(&params, &state.config.clickhouse_database) {
-        Ok(sql) => sql,
-        Err(SearchSqlError::InvalidPlan) => {
-            return (
+    let after = match params.after.as_deref() {
+        Some(raw) if !raw.is_empty() => match PageCursor::decode(raw) {
+            Some(cursor) => Some(cursor),
+            None => {
+                return finish(
+                    "invalid_cursor",
+                    (
+                        StatusCode::BAD_REQUEST,
+                        axum::Json(serde_json::json!({"error": "invalid cursor"})),
+                    ),
+                );
+            }
+        },
+        _ => None,
+    };
+
+    let mut query: SearchQuery = (&params).into();
+    query.after = after;
+
+    let format: TimestampFormat = params
+        .received_at
+        .as_deref()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_default();
+
+    let db = state.config.load().clickhouse_database.clone();
+
+    if let Some(cached) = state.redis.get_cached_search(&query, &db, format).await {
+        return finish(
+            "cache_hit",
+            (
+                StatusCode::OK,
+                axum::Json(serde_json::from_str(&cached).unwrap_or_else(
+                    |_| serde_json::json!({"requests": []}),
+                )),
+            ),
+        );
+    }
+
+    match tokio::time::timeout(Duration::from_secs(5), storage.search(&query)).await {
+        Ok(Ok((results, next_cursor))) => {
+            let rendered: Vec<_> = results.iter().map(|r| r.to_json(format)).collect();
+            let body = match next_cursor {
+                Some(cursor) => {
+                    serde_json::json!({"requests": rendered, "nextCursor": cursor})
+                }
+                None => serde_json::json!({"requests": rendered}),
+            };
+
+            if let Ok(serialized) = serde_json::to_string(&body) {
+                state.redis.set_cached_search(&query, &db, format, &serialized).await;
+            }
+
+            finish("success", (StatusCode::OK, axum::Json(body)))
+        }
+        Ok(Err(SearchError::InvalidQuery(SearchQueryError::InvalidPlan))) => finish(
+            "invalid_query",
+            (
                 StatusCode::BAD_REQUEST,
                 axum::Json(serde_json::json!({"error": "invalid plan"})),
-            );
-        }
-        Err(SearchSqlError::InvalidSlug) => {
-            return (
+            ),
+        ),
+        Ok(Err(SearchError::InvalidQuery(SearchQueryError::InvalidSlug))) => finish(
+            "invalid_query",
+            (
                 StatusCode::BAD_REQUEST,
                 axum::Json(serde_json::json!({"error": "invalid slug"})),
-            );
-        }
-    };
-
-    match tokio::time::timeout(Duration::from_secs(5), clickhouse.query_requests(&sql)).await {
-        Ok(Ok(results)) => (StatusCode::OK, axum::Json(serde_json::json!(results))),
-        Ok(Err(e)) => {
-            tracing::error!(error = %e, "ClickHouse search query failed");
+            ),
+        ),
+        Ok(Err(SearchError::InvalidQuery(SearchQueryError::InvalidCursor))) => finish(
+            "invalid_query",
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                axum::Json(serde_json::json!({"error": "search query failed"})),
+                StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({"error": "invalid cursor"})),
+            ),
+        ),
+        Ok(Err(SearchError::Backend(e))) => {
+            tracing::error!(error = %e, "storage backend search query failed");
+            finish(
+                "backend_error",
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    axum::Json(serde_json::json!({"error": "search query failed"})),
+                ),
             )
         }
         Err(_) => {
-            tracing::error!("ClickHouse search query timed out");
-            (
-                StatusCode::GATEWAY_TIMEOUT,
-                axum::Json(serde_json::json!({"error": "search query timed out"})),
+            tracing::error!("storage backend search query timed out");
+            finish(
+                "timeout",
+                (
+                    StatusCode::GATEWAY_TIMEOUT,
+                    axum::Json(serde_json::json!({"error": "search query timed out"})),
+                ),
             )
         }
     }
@@ -190,34 +215,10 @@ pub async fn search(
 
 #[cfg(test)]
 mod tests {
-    use super::{SearchParams, SearchSqlError, build_search_sql, free_retention_clause_for_plan};
+    use super::{SearchParams, SearchQuery};
 
     #[test]
-    fn free_plan_gets_retention_clause() {
-        let clause =
-            free_retention_clause_for_plan(Some("free")).expect("free plan should be valid");
-        assert_eq!(clause, Some("received_at >= now() - INTERVAL 7 DAY"));
-    }
-
-    #[test]
-    fn pro_and_missing_plan_have_no_clause() {
-        let pro_clause =
-            free_retention_clause_for_plan(Some("pro")).expect("pro plan should be valid");
-        assert_eq!(pro_clause, None);
-
-        let none_clause =
-            free_retention_clause_for_plan(None).expect("missing plan should be valid");
-        assert_eq!(none_clause, None);
-    }
-
-    #[test]
-    fn invalid_plan_is_rejected() {
-        let result = free_retention_clause_for_plan(Some("enterprise"));
-        assert_eq!(result, Err(SearchSqlError::InvalidPlan));
-    }
-
-    #[test]
-    fn build_search_sql_includes_free_plan_retention_clause() {
+    fn search_params_normalize_limit_offset_and_order_into_search_query() {
         let params = SearchParams {
             user_id: "user_123".to_string(),
             plan: Some("free".to_string()),
@@ -229,23 +230,24 @@ mod tests {
             limit: Some(25),
             offset: Some(10),
             order: Some("desc".to_string()),
+            received_at: None,
+            after: None,
         };
 
-        let sql = build_search_sql(&params, "webhooks").expect("sql should build");
+        let query: SearchQuery = (&params).into();
 
-        assert!(sql.contains("FROM `webhooks`.`requests`"));
-        assert!(sql.contains("user_id = 'user_123'"));
-        assert!(sql.contains("received_at >= now() - INTERVAL 7 DAY"));
-        assert!(sql.contains("slug = 'demo_slug'"));
-        assert!(sql.contains("method = 'POST'"));
-        assert!(sql.contains("LIMIT 25 OFFSET 10"));
+        assert_eq!(query.limit, 25);
+        assert_eq!(query.offset, 10);
+        assert!(query.order_desc);
+        assert_eq!(query.slug.as_deref(), Some("demo_slug"));
+        assert_eq!(query.method.as_deref(), Some("POST"));
     }
 
     #[test]
-    fn build_search_sql_omits_retention_for_pro_plan() {
+    fn missing_limit_offset_and_order_fall_back_to_defaults() {
         let params = SearchParams {
             user_id: "user_123".to_string(),
-            plan: Some("pro".to_string()),
+            plan: None,
             slug: None,
             method: None,
             q: None,
@@ -254,53 +256,38 @@ mod tests {
             limit: None,
             offset: None,
             order: None,
+            received_at: None,
+            after: None,
         };
 
-        let sql = build_search_sql(&params, "webhooks").expect("sql should build");
-        assert!(!sql.contains("INTERVAL 7 DAY"));
+        let query: SearchQuery = (&params).into();
+
+        assert_eq!(query.limit, 50);
+        assert_eq!(query.offset, 0);
+        assert!(query.order_desc);
     }
 
     #[test]
-    fn build_search_sql_rejects_invalid_slug() {
+    fn oversized_limit_and_offset_are_clamped() {
         let params = SearchParams {
             user_id: "user_123".to_string(),
-            plan: Some("free".to_string()),
-            slug: Some("../bad".to_string()),
+            plan: None,
+            slug: None,
             method: None,
             q: None,
             from: None,
             to: None,
-            limit: None,
-            offset: None,
-            order: None,
-        };
-
-        let err = build_search_sql(&params, "webhooks").expect_err("invalid slug should fail");
-        assert_eq!(err, SearchSqlError::InvalidSlug);
-    }
-
-    #[test]
-    fn build_search_sql_escapes_inputs_and_handles_negative_timestamps() {
-        let params = SearchParams {
-            user_id: "user'; DROP TABLE requests--".to_string(),
-            plan: None,
-            slug: None,
-            method: None,
-            q: Some("needle'\\\\test".to_string()),
-            from: Some(-1),
-            to: Some(-1001),
-            limit: None,
-            offset: None,
+            limit: Some(10_000),
+            offset: Some(1_000_000),
             order: Some("asc".to_string()),
+            received_at: None,
+            after: None,
         };
 
-        let sql = build_search_sql(&params, "web`hooks").expect("sql should build");
+        let query: SearchQuery = (&params).into();
 
-        assert!(sql.contains("FROM `web``hooks`.`requests`"));
-        assert!(sql.contains("user_id = 'user\\'; DROP TABLE requests--'"));
-        assert!(sql.contains("multiSearchAny(path, ['needle\\'\\\\\\\\test'])"));
-        assert!(sql.contains("received_at >= toDateTime64('-1.999', 3, 'UTC')"));
-        assert!(sql.contains("received_at <= toDateTime64('-2.999', 3, 'UTC')"));
-        assert!(sql.contains("ORDER BY received_at ASC"));
+        assert_eq!(query.limit, 200);
+        assert_eq!(query.offset, 10_000);
+        assert!(!query.order_desc);
     }
 }
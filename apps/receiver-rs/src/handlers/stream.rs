@@ -0,0 +1,101 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::AppState;
+use crate::handlers::auth::verify_bearer_token;
+use crate::handlers::webhook::is_valid_slug;
+
+/// Bounded so one slow/stalled subscriber can't grow memory unboundedly —
+/// generous enough to absorb a short burst of captures without dropping.
+const STREAM_CHANNEL_CAPACITY: usize = 64;
+
+/// `GET /w/{slug}/stream` — Server-Sent Events live tail of newly captured
+/// requests for `slug`. Each event's `data` is the same compact JSON
+/// `BufferedRequest` payload `handlers::webhook::buffer_request` publishes
+/// to Redis; a heartbeat comment frame goes out every 15s (via axum's
+/// `KeepAlive`) to keep the connection alive through proxies that time out
+/// idle streams.
+pub async fn stream_webhook(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let auth = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if !verify_bearer_token(auth, &state.config.load().capture_shared_secret) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            axum::Json(serde_json::json!({"error": "unauthorized"})),
+        )
+            .into_response();
+    }
+
+    if !is_valid_slug(&slug) {
+        return (
+            StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({"error": "invalid_slug"})),
+        )
+            .into_response();
+    }
+
+    let mut pubsub = match state.redis.subscribe_stream(&slug).await {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::warn!(slug, error = %e, "failed to subscribe to request stream channel");
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                axum::Json(serde_json::json!({"error": "stream_unavailable"})),
+            )
+                .into_response();
+        }
+    };
+
+    let (tx, rx) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+    let mut shutdown = state.shutdown.clone();
+
+    tokio::spawn(async move {
+        if *shutdown.borrow() {
+            return;
+        }
+
+        let mut messages = pubsub.on_message();
+        loop {
+            tokio::select! {
+                // Without this arm, a disconnected subscriber (tab closed,
+                // proxy reset) is only noticed on the next `tx.send` — which
+                // on a low-traffic slug may never come, leaking this task and
+                // its dedicated Redis pub/sub connection indefinitely.
+                _ = tx.closed() => break,
+                changed = shutdown.changed() => {
+                    if changed.is_err() || *shutdown.borrow() {
+                        break;
+                    }
+                }
+                msg = messages.next() => {
+                    let Some(msg) = msg else { break };
+                    let Ok(payload) = msg.get_payload::<String>() else { continue };
+                    if tx.send(payload).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let stream = ReceiverStream::new(rx).map(|payload| Ok::<_, Infallible>(Event::default().data(payload)));
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::new().interval(Duration::from_secs(15)).text("heartbeat"))
+        .into_response()
+}
@@ -1,13 +1,20 @@
 use axum::body::Bytes;
 use axum::extract::{Path, State};
-use axum::http::{HeaderMap, Method, StatusCode};
+use axum::http::header::{ACCESS_CONTROL_ALLOW_ORIGIN, ORIGIN};
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode};
 use axum::response::{IntoResponse, Response};
 use std::collections::HashMap;
 
 use crate::convex::types::{now_ms, BufferedRequest};
 use crate::redis::quota::QuotaResult;
+use crate::redis::rate_limit::RateResult;
+use crate::redis::request_buffer::PushOutcome;
 use crate::AppState;
 
+/// Methods advertised in `Access-Control-Allow-Methods` for preflight
+/// requests — mirrors the methods actually routed to `handle_webhook`.
+const CORS_ALLOWED_METHODS: &str = "GET, POST, PUT, PATCH, DELETE, OPTIONS";
+
 const MAX_HEADER_KEY_LEN: usize = 256;
 const MAX_HEADER_VALUE_LEN: usize = 8192;
 
@@ -48,7 +55,11 @@ pub fn is_valid_slug(slug: &str) -> bool {
 /// Extract the real client IP from proxy headers.
 /// Sanitizes the value to contain only valid IP characters (digits, dots, colons, hex)
 /// to prevent XSS via spoofed headers stored in the database.
-fn real_ip(headers: &HeaderMap) -> String {
+///
+/// Also used by `middleware::search_rate_limit` as a rate-limit key — unlike
+/// a query parameter, the caller can't simply pass a different value to mint
+/// a fresh budget.
+pub(crate) fn real_ip(headers: &HeaderMap) -> String {
     let raw = if let Some(ip) = headers.get("cf-connecting-ip").and_then(|v| v.to_str().ok()) {
         ip.to_string()
     } else if let Some(ip) = headers.get("x-real-ip").and_then(|v| v.to_str().ok()) {
@@ -81,7 +92,7 @@ fn cf_connecting_ip(headers: &HeaderMap) -> String {
         .unwrap_or_else(|| real_ip(headers))
 }
 
-/// The main webhook handler: GET/POST/PUT/PATCH/DELETE /w/{slug}/*
+/// The main webhook handler: GET/POST/PUT/PATCH/DELETE/OPTIONS /w/{slug}/*
 pub async fn handle_webhook(
     State(state): State<AppState>,
     method: Method,
@@ -90,12 +101,25 @@ pub async fn handle_webhook(
     query: axum::extract::Query<HashMap<String, String>>,
     body: Bytes,
 ) -> Response {
+    let start = std::time::Instant::now();
+    let body_len = body.len();
+    // Every early return below goes through `finish` so the outcome counter,
+    // handler-latency histogram, and bytes-received counter stay in sync no
+    // matter which branch the request takes.
+    let finish = |outcome: &'static str, resp: Response| -> Response {
+        crate::metrics::record_webhook_request(outcome, body_len, start.elapsed());
+        resp
+    };
+
     if !is_valid_slug(&slug) {
-        return (
-            StatusCode::BAD_REQUEST,
-            axum::Json(serde_json::json!({"error": "invalid_slug"})),
-        )
-            .into_response();
+        return finish(
+            "invalid_slug",
+            (
+                StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({"error": "invalid_slug"})),
+            )
+                .into_response(),
+        );
     }
 
     let req_path = if path.is_empty() {
@@ -110,18 +134,21 @@ pub async fn handle_webhook(
     let endpoint = match state.redis.get_endpoint(&slug).await {
         Some(ep) => {
             if ep.error == "not_found" {
-                return (
-                    StatusCode::NOT_FOUND,
-                    axum::Json(serde_json::json!({"error": "not_found"})),
-                )
-                    .into_response();
+                return finish(
+                    "not_found",
+                    (
+                        StatusCode::NOT_FOUND,
+                        axum::Json(serde_json::json!({"error": "not_found"})),
+                    )
+                        .into_response(),
+                );
             }
             ep
         }
         None => {
             // Cache miss: blocking fetch so we know the endpoint type.
             // Warm quota in parallel to reduce the chance of a blocking
-            // fetch at step 3.
+            // fetch at step 4.
             let convex_q = state.convex.clone();
             let slug_q = slug.clone();
             tokio::spawn(async move {
@@ -131,42 +158,133 @@ pub async fn handle_webhook(
             match state.convex.fetch_and_cache_endpoint(&slug).await {
                 Ok(Some(ep)) => ep,
                 Ok(None) => {
-                    return (
-                        StatusCode::NOT_FOUND,
-                        axum::Json(serde_json::json!({"error": "not_found"})),
-                    )
-                        .into_response();
+                    return finish(
+                        "not_found",
+                        (
+                            StatusCode::NOT_FOUND,
+                            axum::Json(serde_json::json!({"error": "not_found"})),
+                        )
+                            .into_response(),
+                    );
                 }
                 Err(e) => {
                     tracing::warn!(slug, error = %e, "blocking endpoint fetch failed");
+                    // A preflight is never buffered; with the endpoint's
+                    // allowlist unknown, answer it without an allowed origin.
+                    if method == Method::OPTIONS {
+                        return finish("preflight", build_preflight_response(&headers, None));
+                    }
                     // Fetch failed: fall back to buffering optimistically
-                    buffer_request(&state, &slug, &method, &req_path, &headers, &query, &body).await;
-                    return (StatusCode::OK, "OK").into_response();
+                    let outcome =
+                        buffer_request(&state, &slug, &method, &req_path, &headers, &query, &body).await;
+                    return match outcome {
+                        PushOutcome::Rejected => finish(
+                            "spill_buffer_full",
+                            (
+                                StatusCode::SERVICE_UNAVAILABLE,
+                                axum::Json(serde_json::json!({"error": "spill_buffer_full"})),
+                            )
+                                .into_response(),
+                        ),
+                        PushOutcome::Spilled => {
+                            finish("spilled", (StatusCode::OK, "OK").into_response())
+                        }
+                        PushOutcome::Buffered => {
+                            finish("buffered", (StatusCode::OK, "OK").into_response())
+                        }
+                    };
                 }
             }
         }
     };
 
+    // Resolve the Access-Control-Allow-Origin to carry onto every response
+    // below, and answer preflight immediately — it never consumes quota,
+    // hits the filter rules, or gets buffered.
+    let cors_origin = resolve_cors_origin(
+        endpoint.allowed_origins.as_deref(),
+        headers.get(ORIGIN).and_then(|v| v.to_str().ok()),
+    );
+    if method == Method::OPTIONS {
+        return finish("preflight", build_preflight_response(&headers, cors_origin.as_deref()));
+    }
+
     // 2. Check expiry
     if endpoint.is_expired() {
-        return (
-            StatusCode::GONE,
-            axum::Json(serde_json::json!({"error": "expired"})),
-        )
-            .into_response();
+        return finish(
+            "expired",
+            (
+                StatusCode::GONE,
+                axum::Json(serde_json::json!({"error": "expired"})),
+            )
+                .into_response(),
+        );
     }
 
-    // 3. Atomic quota check via Redis Lua script (per-user when userId present).
+    // 2.5. Evaluate per-endpoint accept/reject rules before quota/buffering,
+    // so rejected requests never consume quota or reach ClickHouse/Convex.
+    if let Some(rules) = endpoint.filter_rules.as_deref().filter(|r| !r.is_empty()) {
+        let ruleset = state.filters.get_or_parse(&slug, rules);
+        let content_type = headers
+            .get(axum::http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+        let client_ip = cf_connecting_ip(&headers);
+        let filter_input = crate::filter::FilterInput {
+            method: method.as_str(),
+            path: &req_path,
+            ip: &client_ip,
+            content_type,
+            headers: &headers,
+            body_size: body.len(),
+        };
+
+        if let crate::filter::Verdict::Reject(reason) = ruleset.evaluate(&filter_input) {
+            state.redis.record_filter_rejection(&slug).await;
+            tracing::info!(slug, reason, "webhook rejected by filter rule");
+            return finish(
+                "rejected_by_filter",
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    axum::Json(serde_json::json!({"error": "rejected_by_filter", "reason": reason})),
+                )
+                    .into_response(),
+            );
+        }
+    }
+
+    // 3. Burst rate limit via a sliding-window-log Lua script, ahead of the
+    // monthly quota counter — a user with remaining quota can still be
+    // throttled if they're sending requests faster than the window allows.
+    if let RateResult::Denied { retry_after_ms } = state
+        .redis
+        .check_burst_rate(&slug, endpoint.user_id.as_deref())
+        .await
+    {
+        return finish(
+            "rate_limited",
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                axum::Json(serde_json::json!({"error": "rate_limited", "retryAfterMs": retry_after_ms})),
+            )
+                .into_response(),
+        );
+    }
+
+    // 4. Atomic quota check via Redis Lua script (per-user when userId present).
     // On cache miss, block to fetch fresh quota from Convex so that all endpoints
     // (guest ephemeral, user ephemeral, and persistent) are strictly enforced.
     match state.redis.check_quota(&slug, endpoint.user_id.as_deref()).await {
         QuotaResult::Allowed => {}
         QuotaResult::Exceeded => {
-            return (
-                StatusCode::TOO_MANY_REQUESTS,
-                axum::Json(serde_json::json!({"error": "quota_exceeded"})),
-            )
-                .into_response();
+            return finish(
+                "quota_exceeded",
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    axum::Json(serde_json::json!({"error": "quota_exceeded"})),
+                )
+                    .into_response(),
+            );
         }
         QuotaResult::NotFound => {
             if let Err(e) = state.convex.fetch_and_cache_quota(&slug).await {
@@ -176,11 +294,14 @@ pub async fn handle_webhook(
             match state.redis.check_quota(&slug, endpoint.user_id.as_deref()).await {
                 QuotaResult::Allowed => {}
                 QuotaResult::Exceeded => {
-                    return (
-                        StatusCode::TOO_MANY_REQUESTS,
-                        axum::Json(serde_json::json!({"error": "quota_exceeded"})),
-                    )
-                        .into_response();
+                    return finish(
+                        "quota_exceeded",
+                        (
+                            StatusCode::TOO_MANY_REQUESTS,
+                            axum::Json(serde_json::json!({"error": "quota_exceeded"})),
+                        )
+                            .into_response(),
+                    );
                 }
                 QuotaResult::NotFound => {
                     tracing::warn!(slug, "quota still not found after blocking fetch — failing open");
@@ -189,26 +310,46 @@ pub async fn handle_webhook(
         }
     }
 
-    // 4. Dedup: skip buffering if an identical request arrived within 2s
+    // 5. Dedup: skip buffering if an identical request arrived within 2s
     //    (catches Cloudflare multi-path duplicate delivery under burst traffic).
     let client_ip = cf_connecting_ip(&headers);
     if !state.redis.check_dedup(&slug, method.as_str(), &req_path, &body, &client_ip).await {
         tracing::debug!(slug, "duplicate request detected, skipping buffer");
         if let Some(mock) = &endpoint.mock_response {
-            return build_mock_response(mock);
+            return finish("deduped", build_mock_response(mock, cors_origin.as_deref()));
         }
-        return (StatusCode::OK, "OK").into_response();
+        return finish(
+            "deduped",
+            with_cors_header((StatusCode::OK, "OK").into_response(), cors_origin.as_deref()),
+        );
     }
 
-    // 5. Buffer the request
-    buffer_request(&state, &slug, &method, &req_path, &headers, &query, &body).await;
+    // 6. Buffer the request
+    let outcome = buffer_request(&state, &slug, &method, &req_path, &headers, &query, &body).await;
+    if outcome == PushOutcome::Rejected {
+        return finish(
+            "spill_buffer_full",
+            with_cors_header(
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    axum::Json(serde_json::json!({"error": "spill_buffer_full"})),
+                )
+                    .into_response(),
+                cors_origin.as_deref(),
+            ),
+        );
+    }
 
-    // 6. Return mock response or "OK"
+    // 7. Return mock response or "OK"
     if let Some(mock) = &endpoint.mock_response {
-        return build_mock_response(mock);
+        return finish("mock", build_mock_response(mock, cors_origin.as_deref()));
     }
 
-    (StatusCode::OK, "OK").into_response()
+    let outcome_label = if outcome == PushOutcome::Spilled { "spilled" } else { "buffered" };
+    finish(
+        outcome_label,
+        with_cors_header((StatusCode::OK, "OK").into_response(), cors_origin.as_deref()),
+    )
 }
 
 /// Also handle the case where no trailing path is provided: /w/{slug}
@@ -231,7 +372,7 @@ async fn buffer_request(
     headers: &HeaderMap,
     query: &axum::extract::Query<HashMap<String, String>>,
     body: &Bytes,
-) {
+) -> PushOutcome {
     let mut header_map = HashMap::new();
     for (key, value) in headers.iter() {
         let name = key.as_str();
@@ -255,12 +396,84 @@ async fn buffer_request(
         query_params: query.0.clone(),
         ip,
         received_at: now_ms(),
+        attempts: 0,
     };
 
-    state.redis.push_request(slug, &buffered).await;
+    let outcome = state.redis.push_request(slug, &buffered).await;
+
+    // Fan out to any live-tail subscribers (see `handlers::stream`) — best
+    // effort, published after the authoritative buffer write so a publish
+    // hiccup never affects delivery of the request itself.
+    if outcome != PushOutcome::Rejected
+        && let Ok(event) = serde_json::to_string(&buffered)
+    {
+        state.redis.publish_stream_event(slug, &event).await;
+    }
+
+    outcome
+}
+
+/// Resolve the `Access-Control-Allow-Origin` value for a request against an
+/// endpoint's configured `allowed_origins`. Mirrors the corrected actix-web
+/// CORS behavior: with one or more allowed origins configured, only the
+/// origin matching the request's `Origin` header is echoed back — never a
+/// blanket `*` — so the browser's per-origin check still applies. `None`/empty
+/// `allowed_origins` means the endpoint hasn't opted into an allowlist, so
+/// every origin is allowed, matching the behavior before this field existed.
+fn resolve_cors_origin(allowed_origins: Option<&[String]>, origin: Option<&str>) -> Option<String> {
+    match allowed_origins {
+        None => Some("*".to_string()),
+        Some(allowed) if allowed.is_empty() => Some("*".to_string()),
+        Some(allowed) => allowed.iter().find(|o| Some(o.as_str()) == origin).cloned(),
+    }
+}
+
+/// Set `Access-Control-Allow-Origin` on `resp` if `allow_origin` resolved to
+/// one (it won't have if the endpoint has an allowlist and the request's
+/// `Origin` didn't match any entry).
+fn with_cors_header(mut resp: Response, allow_origin: Option<&str>) -> Response {
+    if let Some(origin) = allow_origin
+        && let Ok(value) = HeaderValue::from_str(origin)
+    {
+        resp.headers_mut().insert(ACCESS_CONTROL_ALLOW_ORIGIN, value);
+    }
+    resp
 }
 
-fn build_mock_response(mock: &crate::convex::types::MockResponse) -> Response {
+/// Answer a CORS preflight (`OPTIONS /w/{slug}/*`) with the already-resolved
+/// `allow_origin` (see `resolve_cors_origin`). Never
+/// touches quota, filters, or the buffer — preflight isn't a real delivery.
+fn build_preflight_response(headers: &HeaderMap, allow_origin: Option<&str>) -> Response {
+    let mut builder = axum::http::Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header("access-control-allow-methods", CORS_ALLOWED_METHODS)
+        .header("access-control-max-age", "86400");
+
+    // Echo the requested headers back verbatim, same as the methods list —
+    // this endpoint has no fixed allowlist of request headers to enforce.
+    if let Some(requested) = headers
+        .get("access-control-request-headers")
+        .and_then(|v| v.to_str().ok())
+    {
+        builder = builder.header("access-control-allow-headers", requested);
+    }
+
+    let resp = builder
+        .body(axum::body::Body::empty())
+        .unwrap_or_else(|_| {
+            axum::http::Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        });
+
+    with_cors_header(resp, allow_origin)
+}
+
+fn build_mock_response(
+    mock: &crate::convex::types::MockResponse,
+    allow_origin: Option<&str>,
+) -> Response {
     let status_code = u16::try_from(mock.status)
         .ok()
         .and_then(|s| StatusCode::from_u16(s).ok())
@@ -289,12 +502,14 @@ fn build_mock_response(mock: &crate::convex::types::MockResponse) -> Response {
         builder = builder.header(key.as_str(), value.as_str());
     }
 
-    builder
+    let resp = builder
         .body(axum::body::Body::from(mock.body.clone()))
         .unwrap_or_else(|_| {
             axum::http::Response::builder()
                 .status(StatusCode::OK)
                 .body(axum::body::Body::from("OK"))
                 .unwrap()
-        })
+        });
+
+    with_cors_header(resp, allow_origin)
 }
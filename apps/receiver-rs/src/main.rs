@@ -1,12 +1,23 @@
 mod clickhouse;
 mod config;
 mod convex;
+mod filter;
 mod handlers;
+mod metrics;
+mod middleware;
+#[cfg(feature = "mocks")]
+mod mocks;
+mod redact;
 mod redis;
+mod spill;
+mod storage;
+mod time;
 mod workers;
 
+use std::sync::Arc;
 use std::time::Duration;
 
+use arc_swap::ArcSwap;
 use axum::Router;
 use axum::routing::{any, get, post};
 use tokio::net::TcpListener;
@@ -17,8 +28,9 @@ use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::trace::TraceLayer;
 
 use clickhouse::client::ClickHouseClient;
-use config::Config;
+use config::{Config, SharedConfig};
 use convex::client::ConvexClient;
+use filter::FilterCache;
 use redis::RedisState;
 
 const MAX_BODY_SIZE: usize = 100 * 1024; // 100KB
@@ -28,56 +40,76 @@ const MAX_BODY_SIZE: usize = 100 * 1024; // 100KB
 pub struct AppState {
     pub redis: RedisState,
     pub convex: ConvexClient,
-    pub config: Config,
+    pub config: SharedConfig,
     pub clickhouse: Option<ClickHouseClient>,
+    /// Backend-neutral handle onto the same store `clickhouse` points at —
+    /// see `storage::StorageBackend`. `handlers::search` uses this instead
+    /// of `clickhouse` directly; the flush/retention workers still take the
+    /// concrete client (see the module doc comment on `storage` for why).
+    pub storage: Option<Arc<dyn storage::StorageBackend>>,
+    /// `true` once shutdown has started — `health` reports `"draining"` and
+    /// 503 while this holds, so load balancers stop routing new traffic here.
+    pub shutdown: watch::Receiver<bool>,
+    /// Per-slug parsed accept/reject rule cache (see `filter::FilterCache`).
+    pub filters: FilterCache,
+    /// Liveness/restart snapshot for every supervised background worker —
+    /// see `workers::supervisor` and `handlers::health`.
+    pub workers: workers::supervisor::WorkerRegistry,
 }
 
 #[tokio::main]
 async fn main() {
     // Load config
-    let config = Config::from_env();
-
-    // Initialize tracing
-    let log_level = if config.debug { "debug" } else { "info" };
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-                format!("webhooks_receiver={log_level},tower_http=info").into()
-            }),
-        )
-        .init();
+    let config: SharedConfig = Arc::new(ArcSwap::from_pointee(Config::from_env()));
+
+    // Initialize tracing. `LOG_JSON` picks structured JSON output for log
+    // pipelines that parse fields instead of greping text — see
+    // `Config::log_json`'s doc comment for why it's a cold (restart-only)
+    // field.
+    let log_level = if config.load().debug { "debug" } else { "info" };
+    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| format!("webhooks_receiver={log_level},tower_http=info").into());
+
+    if config.load().log_json {
+        tracing_subscriber::fmt().json().with_env_filter(env_filter).init();
+    } else {
+        tracing_subscriber::fmt().with_env_filter(env_filter).init();
+    }
+
+    // Open the local disk spill store before Redis — push_request needs it
+    // ready the moment Redis is unreachable, not after the fact.
+    let spill = spill::SpillStore::open(&config.load().spill_dir)
+        .expect("failed to open disk spill store");
 
     // Connect to Redis
-    let redis_url = config.redis_url();
-    let redis = RedisState::new(
-        &redis_url,
-        config.endpoint_cache_ttl_secs,
-        config.quota_cache_ttl_secs,
-    )
-    .await
-    .expect("failed to connect to Redis");
+    let redis_url = config.load().redis_url();
+    let redis = RedisState::new(&redis_url, config.clone(), spill)
+        .await
+        .expect("failed to connect to Redis");
 
     tracing::info!(
-        host = config.redis_host,
-        port = config.redis_port,
+        host = config.load().redis_host,
+        port = config.load().redis_port,
         "connected to Redis"
     );
 
     // Create Convex client
-    let convex = ConvexClient::new(&config, redis.clone());
+    let convex = ConvexClient::new(&config.load(), redis.clone());
 
     // Initialize ClickHouse client (optional)
-    let clickhouse = if let Some(url) = &config.clickhouse_url {
+    let clickhouse = if let Some(url) = &config.load().clickhouse_url {
         let ch = ClickHouseClient::new(
             url,
-            &config.clickhouse_user,
-            &config.clickhouse_password,
-            &config.clickhouse_database,
+            &config.load().clickhouse_user,
+            &config.load().clickhouse_password,
+            &config.load().clickhouse_database,
+            config.load().clickhouse_compress,
+            config.load().clickhouse_compress_min_body_bytes,
         );
         if ch.ping().await {
             tracing::info!(
                 url,
-                db = config.clickhouse_database,
+                db = config.load().clickhouse_database,
                 "ClickHouse dual-write enabled"
             );
         } else {
@@ -95,76 +127,159 @@ async fn main() {
     // Shutdown signal
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
 
-    // Spawn background workers
+    // Spawn background workers. Each runs under `workers::supervisor`, which
+    // restarts a panicked worker with backoff and tracks its liveness in
+    // `worker_registry` for `handlers::health` to report.
+    let worker_registry = workers::supervisor::WorkerRegistry::new();
     workers::flush::spawn_flush_workers(
+        &worker_registry,
         redis.clone(),
         convex.clone(),
         clickhouse.clone(),
-        config.flush_workers,
-        config.batch_max_size,
-        Duration::from_millis(config.flush_interval_ms),
+        config.clone(),
+        config.load().flush_workers,
+        shutdown_rx.clone(),
+    );
+    workers::cache_warmer::spawn_cache_warmer(
+        &worker_registry,
+        redis.clone(),
+        convex.clone(),
         shutdown_rx.clone(),
     );
-    workers::cache_warmer::spawn_cache_warmer(redis.clone(), convex.clone(), shutdown_rx.clone());
     workers::clickhouse_retention::spawn_clickhouse_retention_worker(
+        &worker_registry,
         convex.clone(),
         clickhouse.clone(),
         shutdown_rx.clone(),
     );
+    workers::spill_reconciler::spawn_spill_reconciler(
+        &worker_registry,
+        redis.clone(),
+        config.clone(),
+        shutdown_rx.clone(),
+    );
+
+    // SIGHUP triggers the same hot-reload path as `POST /admin/reload`.
+    spawn_reload_on_sighup(config.clone());
 
     // Build app state
     let state = AppState {
-        redis,
+        redis: redis.clone(),
         convex,
         config: config.clone(),
+        storage: clickhouse
+            .clone()
+            .map(|ch| Arc::new(ch) as Arc<dyn storage::StorageBackend>),
         clickhouse,
+        shutdown: shutdown_rx.clone(),
+        filters: FilterCache::new(),
+        workers: worker_registry,
     };
 
-    // CORS: allow all origins only on public webhook capture endpoints.
-    // Internal endpoints (/search, /internal/*) have no CORS (server-to-server only).
+    // CORS: allow all origins on health/metrics (no per-caller identity to
+    // restrict on). Webhook capture routes answer CORS themselves, per
+    // endpoint's `allowed_origins` (see `handlers::webhook`) — they must NOT
+    // carry this blanket layer, since tower-http's `CorsLayer` intercepts and
+    // answers OPTIONS preflight before the request ever reaches the handler.
     let public_cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
 
-    // Public routes: webhook capture + health (need permissive CORS)
+    // Health/metrics get the blanket CORS layer. Webhook capture routes are
+    // a separate, unlayered router merged in below — they answer CORS
+    // themselves, per endpoint (see above).
     let public_routes = Router::new()
         .route("/health", get(handlers::health::health))
-        .route("/w/{slug}/{*path}", any(handlers::webhook::handle_webhook))
-        .route("/w/{slug}", any(handlers::webhook::handle_webhook_no_path))
+        .route("/metrics", get(handlers::metrics::metrics))
         .layer(public_cors);
 
+    let webhook_routes = Router::new()
+        .route("/w/{slug}/{*path}", any(handlers::webhook::handle_webhook))
+        .route("/w/{slug}", any(handlers::webhook::handle_webhook_no_path));
+
+    // Search routes get their own per-source-IP rate limit layer (see
+    // `middleware::search_rate_limit`) — scoped here rather than on all of
+    // `internal_routes` since they're the ones fanning out to ClickHouse.
+    let search_routes = Router::new()
+        .route("/search", get(handlers::search::search))
+        .route("/search/facets", get(handlers::facets::facets))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::search_rate_limit,
+        ));
+
     // Internal routes: no CORS (server-to-server only, authenticated via shared secret)
     let internal_routes = Router::new()
-        .route("/search", get(handlers::search::search))
+        .merge(search_routes)
+        .route("/w/{slug}/stream", get(handlers::stream::stream_webhook))
+        .route(
+            "/endpoints/{slug}/requests/search",
+            get(handlers::endpoint_search::search_endpoint_requests),
+        )
         .route(
             "/internal/cache-invalidate/{slug}",
             post(handlers::cache_invalidate::cache_invalidate),
-        );
+        )
+        .route("/admin/reload", post(handlers::admin::reload_config));
 
     // Build router
     let app = public_routes
+        .merge(webhook_routes)
         .merge(internal_routes)
         .layer(RequestBodyLimitLayer::new(MAX_BODY_SIZE))
         .layer(TraceLayer::new_for_http())
         .with_state(state);
 
     // Start server
-    let addr = format!("0.0.0.0:{}", config.port);
+    let addr = format!("0.0.0.0:{}", config.load().port);
     let listener = TcpListener::bind(&addr)
         .await
         .expect("failed to bind address");
 
-    tracing::info!(port = config.port, "webhook receiver starting");
+    tracing::info!(port = config.load().port, "webhook receiver starting");
 
     // Serve with graceful shutdown
     axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal(shutdown_tx))
+        .with_graceful_shutdown(shutdown_signal(shutdown_tx, redis, config.clone()))
         .await
         .expect("server error");
 }
 
-async fn shutdown_signal(shutdown_tx: watch::Sender<bool>) {
+/// Re-read the environment on every `SIGHUP` and hot-swap the config, using
+/// the same validation/cold-field-rejection path as `POST /admin/reload`.
+#[cfg(unix)]
+fn spawn_reload_on_sighup(config: SharedConfig) {
+    tokio::spawn(async move {
+        let mut sighup = match signal::unix::signal(signal::unix::SignalKind::hangup()) {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to install SIGHUP handler, hot-reload via signal disabled");
+                return;
+            }
+        };
+
+        loop {
+            sighup.recv().await;
+            match config.load().reload() {
+                Ok(new_config) => {
+                    config.store(Arc::new(new_config));
+                    tracing::info!("config reloaded via SIGHUP");
+                }
+                Err(e) => tracing::warn!(error = %e, "config reload via SIGHUP rejected"),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn spawn_reload_on_sighup(_config: SharedConfig) {}
+
+/// Waits for Ctrl-C/SIGTERM, then notifies workers via `shutdown_tx` and
+/// blocks (up to `Config::shutdown_grace_secs`) for the Redis request
+/// buffers to drain before returning, so the flush workers get a real chance
+/// to empty them rather than racing a fixed sleep.
+async fn shutdown_signal(shutdown_tx: watch::Sender<bool>, redis: RedisState, config: SharedConfig) {
     let ctrl_c = async {
         signal::ctrl_c().await.expect("failed to listen for ctrl+c");
     };
@@ -185,13 +300,34 @@ async fn shutdown_signal(shutdown_tx: watch::Sender<bool>) {
         _ = terminate => {}
     }
 
-    tracing::info!("shutdown signal received, flushing pending requests...");
+    let pending = redis.total_buffered_len().await;
+    tracing::info!(pending, "shutdown signal received, draining buffered requests...");
 
-    // Notify workers to drain and exit
+    // Notify workers and /health to start draining
     let _ = shutdown_tx.send(true);
 
-    // Give workers time to flush
-    tokio::time::sleep(Duration::from_secs(5)).await;
+    let grace = Duration::from_secs(config.load().shutdown_grace_secs);
+    let drained = tokio::time::timeout(grace, async {
+        loop {
+            if redis.total_buffered_len().await == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    })
+    .await;
+
+    let remaining = redis.total_buffered_len().await;
+    let flushed = pending.saturating_sub(remaining);
 
-    tracing::info!("shutdown complete");
+    if drained.is_err() {
+        tracing::warn!(
+            flushed,
+            dropped = remaining,
+            grace_secs = grace.as_secs(),
+            "shutdown grace period elapsed before drain completed"
+        );
+    } else {
+        tracing::info!(flushed, "shutdown drain complete, all buffered requests flushed");
+    }
 }
@@ -0,0 +1,343 @@
+//! Prometheus metrics for the gateway's Convex client, circuit breaker,
+//! quota decisions, ClickHouse queries, flush-worker batches, and the
+//! `/search` handler — scraped via `GET /metrics`.
+//!
+//! There's exactly one `ConvexClient`/`RedisState` per process, so a single
+//! process-wide registry behind a `OnceLock` is simpler than threading a
+//! metrics handle through every call site (mirrors how `crate::filter` and
+//! `crate::redact` use process-wide caches rather than per-request state).
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use prometheus::{
+    Encoder, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Registry,
+    TextEncoder, register_histogram_vec_with_registry, register_histogram_with_registry,
+    register_int_counter_vec_with_registry, register_int_counter_with_registry,
+    register_int_gauge_vec_with_registry, register_int_gauge_with_registry,
+};
+
+use crate::convex::circuit_breaker::CircuitState;
+use crate::convex::client::ConvexError;
+use crate::redis::quota::QuotaResult;
+
+struct Metrics {
+    registry: Registry,
+    convex_requests_total: IntCounterVec,
+    convex_request_duration_seconds: HistogramVec,
+    convex_errors_total: IntCounterVec,
+    circuit_state: IntGauge,
+    quota_checks_total: IntCounterVec,
+    webhook_requests_total: IntCounterVec,
+    webhook_request_duration_seconds: Histogram,
+    webhook_bytes_received_total: IntCounter,
+    buffer_depth: IntGaugeVec,
+    buffer_batch_size: Histogram,
+    clickhouse_rows_inserted_total: IntCounter,
+    clickhouse_operation_duration_seconds: HistogramVec,
+    clickhouse_errors_total: IntCounterVec,
+    spill_depth: IntGauge,
+    search_requests_total: IntCounterVec,
+    search_request_duration_seconds: Histogram,
+    flush_batch_duration_seconds: Histogram,
+}
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(|| {
+        let registry = Registry::new();
+
+        let convex_requests_total = register_int_counter_vec_with_registry!(
+            "convex_requests_total",
+            "Total ConvexClient calls, by method and outcome (success/error).",
+            &["method", "outcome"],
+            registry
+        )
+        .expect("failed to register convex_requests_total");
+
+        let convex_request_duration_seconds = register_histogram_vec_with_registry!(
+            "convex_request_duration_seconds",
+            "ConvexClient call latency in seconds, by method.",
+            &["method"],
+            registry
+        )
+        .expect("failed to register convex_request_duration_seconds");
+
+        let convex_errors_total = register_int_counter_vec_with_registry!(
+            "convex_errors_total",
+            "ConvexClient errors, by ConvexError variant.",
+            &["variant"],
+            registry
+        )
+        .expect("failed to register convex_errors_total");
+
+        let circuit_state = register_int_gauge_with_registry!(
+            "convex_circuit_breaker_state",
+            "Current circuit breaker state (0=closed, 1=half-open, 2=open).",
+            registry
+        )
+        .expect("failed to register convex_circuit_breaker_state");
+
+        let quota_checks_total = register_int_counter_vec_with_registry!(
+            "quota_checks_total",
+            "RedisState::check_quota outcomes, by result.",
+            &["result"],
+            registry
+        )
+        .expect("failed to register quota_checks_total");
+
+        let webhook_requests_total = register_int_counter_vec_with_registry!(
+            "webhook_requests_total",
+            "Total handle_webhook calls, by outcome.",
+            &["outcome"],
+            registry
+        )
+        .expect("failed to register webhook_requests_total");
+
+        let webhook_request_duration_seconds = register_histogram_with_registry!(
+            "webhook_request_duration_seconds",
+            "handle_webhook latency in seconds, from entry to response.",
+            registry
+        )
+        .expect("failed to register webhook_request_duration_seconds");
+
+        let webhook_bytes_received_total = register_int_counter_with_registry!(
+            "webhook_bytes_received_total",
+            "Total request body bytes received by handle_webhook.",
+            registry
+        )
+        .expect("failed to register webhook_bytes_received_total");
+
+        let buffer_depth = register_int_gauge_vec_with_registry!(
+            "redis_buffer_depth",
+            "Pending buffered requests per active slug (RedisState's buf: lists).",
+            &["slug"],
+            registry
+        )
+        .expect("failed to register redis_buffer_depth");
+
+        let buffer_batch_size = register_histogram_with_registry!(
+            "redis_buffer_batch_size",
+            "Size of batches taken from a slug's buffer by RedisState::take_batch.",
+            registry
+        )
+        .expect("failed to register redis_buffer_batch_size");
+
+        let clickhouse_rows_inserted_total = register_int_counter_with_registry!(
+            "clickhouse_rows_inserted_total",
+            "Total rows successfully inserted via ClickHouseClient::insert_requests.",
+            registry
+        )
+        .expect("failed to register clickhouse_rows_inserted_total");
+
+        let clickhouse_operation_duration_seconds = register_histogram_vec_with_registry!(
+            "clickhouse_operation_duration_seconds",
+            "ClickHouseClient call latency in seconds, by operation (insert/query/query_page/query_json).",
+            &["operation"],
+            registry
+        )
+        .expect("failed to register clickhouse_operation_duration_seconds");
+
+        let clickhouse_errors_total = register_int_counter_vec_with_registry!(
+            "clickhouse_errors_total",
+            "ClickHouseClient errors, by failure kind (network/http_status/serialize/compression).",
+            &["kind"],
+            registry
+        )
+        .expect("failed to register clickhouse_errors_total");
+
+        let spill_depth = register_int_gauge_with_registry!(
+            "disk_spill_depth",
+            "Requests currently parked in the local disk spill store (SpillStore), waiting for Redis to come back.",
+            registry
+        )
+        .expect("failed to register disk_spill_depth");
+
+        let search_requests_total = register_int_counter_vec_with_registry!(
+            "search_requests_total",
+            "Total GET /search calls, by outcome (cache_hit/success/invalid_query/backend_error/timeout/unauthorized/missing_user_id/unavailable/invalid_cursor).",
+            &["outcome"],
+            registry
+        )
+        .expect("failed to register search_requests_total");
+
+        let search_request_duration_seconds = register_histogram_with_registry!(
+            "search_request_duration_seconds",
+            "GET /search latency in seconds, from entry to response (including a cache hit).",
+            registry
+        )
+        .expect("failed to register search_request_duration_seconds");
+
+        let flush_batch_duration_seconds = register_histogram_with_registry!(
+            "flush_batch_duration_seconds",
+            "Wall-clock time of a flush worker's Convex capture_batch call per drained batch.",
+            registry
+        )
+        .expect("failed to register flush_batch_duration_seconds");
+
+        Metrics {
+            registry,
+            convex_requests_total,
+            convex_request_duration_seconds,
+            convex_errors_total,
+            circuit_state,
+            quota_checks_total,
+            webhook_requests_total,
+            webhook_request_duration_seconds,
+            webhook_bytes_received_total,
+            buffer_depth,
+            buffer_batch_size,
+            clickhouse_rows_inserted_total,
+            clickhouse_operation_duration_seconds,
+            clickhouse_errors_total,
+            spill_depth,
+            search_requests_total,
+            search_request_duration_seconds,
+            flush_batch_duration_seconds,
+        }
+    })
+}
+
+fn error_variant_label(err: &ConvexError) -> &'static str {
+    match err {
+        ConvexError::CircuitOpen => "circuit_open",
+        ConvexError::Network(_) => "network",
+        ConvexError::ServerError(_, _) => "server_error",
+        ConvexError::ClientError(_, _) => "client_error",
+        ConvexError::ParseError(_) => "parse_error",
+        ConvexError::ResponseTooLarge => "response_too_large",
+    }
+}
+
+/// Record one completed `ConvexClient` method call: request count (by method
+/// + outcome), latency, and — on failure — the `ConvexError` variant.
+pub fn record_convex_call<T>(
+    method: &'static str,
+    result: &Result<T, ConvexError>,
+    elapsed: Duration,
+) {
+    let m = metrics();
+    let outcome = if result.is_ok() { "success" } else { "error" };
+    m.convex_requests_total
+        .with_label_values(&[method, outcome])
+        .inc();
+    m.convex_request_duration_seconds
+        .with_label_values(&[method])
+        .observe(elapsed.as_secs_f64());
+    if let Err(err) = result {
+        m.convex_errors_total
+            .with_label_values(&[error_variant_label(err)])
+            .inc();
+    }
+}
+
+/// Update the circuit-breaker state gauge. Called from `record_success_sync`/
+/// `record_failure_sync` with the breaker's locally cached state, which
+/// follows Redis only as often as the breaker syncs with it.
+pub fn set_circuit_state(state: CircuitState) {
+    let value = match state {
+        CircuitState::Closed => 0,
+        CircuitState::HalfOpen => 1,
+        CircuitState::Open => 2,
+    };
+    metrics().circuit_state.set(value);
+}
+
+/// Record one `RedisState::check_quota` outcome.
+pub fn record_quota_check(result: &QuotaResult) {
+    let label = match result {
+        QuotaResult::Allowed => "allowed",
+        QuotaResult::Exceeded => "exceeded",
+        QuotaResult::NotFound => "not_found",
+    };
+    metrics()
+        .quota_checks_total
+        .with_label_values(&[label])
+        .inc();
+}
+
+/// Record one completed `handle_webhook` call: outcome count, handler
+/// latency, and bytes received. `outcome` is one of `buffered`,
+/// `quota_exceeded`, `not_found`, `expired`, `deduped`, `mock`, or one of the
+/// other early-return labels the handler produces (e.g. `invalid_slug`,
+/// `rejected_by_filter`, `rate_limited`).
+pub fn record_webhook_request(outcome: &'static str, body_len: usize, elapsed: Duration) {
+    let m = metrics();
+    m.webhook_requests_total.with_label_values(&[outcome]).inc();
+    m.webhook_request_duration_seconds.observe(elapsed.as_secs_f64());
+    m.webhook_bytes_received_total.inc_by(body_len as u64);
+}
+
+/// Set the buffer-depth gauge for one slug to an absolute value. Called from
+/// `RedisState::buffer_len`, which is read directly (not just incremented by
+/// push/take deltas) so the gauge self-corrects if it ever drifts.
+pub fn set_buffer_depth(slug: &str, depth: usize) {
+    metrics().buffer_depth.with_label_values(&[slug]).set(depth as i64);
+}
+
+/// Bump the buffer-depth gauge for one slug by `delta` (positive on push,
+/// negative on take) without an extra Redis round trip.
+pub fn add_buffer_depth(slug: &str, delta: i64) {
+    metrics().buffer_depth.with_label_values(&[slug]).add(delta as f64);
+}
+
+/// Record the size of a batch taken from a slug's buffer.
+pub fn record_batch_size(size: usize) {
+    metrics().buffer_batch_size.observe(size as f64);
+}
+
+/// Record the latency of one `ClickHouseClient` insert or query call. Rows
+/// written are counted separately by `record_clickhouse_rows_inserted`.
+pub fn record_clickhouse_op(operation: &'static str, elapsed: Duration) {
+    metrics()
+        .clickhouse_operation_duration_seconds
+        .with_label_values(&[operation])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// Record rows successfully written by `ClickHouseClient::insert_requests`.
+pub fn record_clickhouse_rows_inserted(count: usize) {
+    metrics().clickhouse_rows_inserted_total.inc_by(count as u64);
+}
+
+/// Record a `ClickHouseClient` failure, labeled by kind: `network`,
+/// `http_status`, `serialize`, or `compression`.
+pub fn record_clickhouse_error(kind: &'static str) {
+    metrics().clickhouse_errors_total.with_label_values(&[kind]).inc();
+}
+
+/// Set the disk-spill depth gauge to an absolute value. Called from
+/// `SpillStore::append`/`drain_oldest`, which are read directly (not just
+/// incremented by deltas) so the gauge self-corrects if it ever drifts.
+pub fn set_spill_depth(depth: usize) {
+    metrics().spill_depth.set(depth as i64);
+}
+
+/// Record one completed `GET /search` call: outcome count and handler
+/// latency. `outcome` is one of `cache_hit`, `success`, `invalid_query`,
+/// `backend_error`, `timeout`, or an early-return label (`unauthorized`,
+/// `missing_user_id`, `unavailable`, `invalid_cursor`).
+pub fn record_search_request(outcome: &'static str, elapsed: Duration) {
+    let m = metrics();
+    m.search_requests_total.with_label_values(&[outcome]).inc();
+    m.search_request_duration_seconds.observe(elapsed.as_secs_f64());
+}
+
+/// Record the wall-clock time of one flush worker's `capture_batch` call —
+/// see `workers::flush::drain_slug`.
+pub fn record_flush_batch_duration(elapsed: Duration) {
+    metrics().flush_batch_duration_seconds.observe(elapsed.as_secs_f64());
+}
+
+/// Render every metric in Prometheus text exposition format for `GET /metrics`.
+pub fn render() -> String {
+    let m = metrics();
+    let families = m.registry.gather();
+    let mut buf = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&families, &mut buf) {
+        tracing::error!(error = %e, "failed to encode Prometheus metrics");
+        return String::new();
+    }
+    String::from_utf8(buf).unwrap_or_default()
+}
@@ -0,0 +1,63 @@
+//! Tower/Axum middleware for the receiver's HTTP layer — currently just the
+//! per-source sliding-window rate limit guarding the search endpoints
+//! (see `main`, which applies this via `.layer(...)` to `/search` and
+//! `/search/facets` only — the webhook capture path has its own
+//! `RedisState::check_burst_rate`).
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::AppState;
+use crate::handlers::webhook::real_ip;
+use crate::redis::rate_limit::RateResult;
+
+const RATE_LIMIT_WINDOW_SECS: u64 = 60;
+
+/// Per-source-IP sliding-window rate limit for the search endpoints, backed
+/// by `RedisState::check_rate` — the same sliding-window-log limiter
+/// `check_burst_rate` uses for webhook capture. Returns `429 Too Many
+/// Requests` with a `Retry-After` header once the caller's per-minute budget
+/// is exhausted; fails open if Redis itself is unreachable (`check_rate`
+/// already does this, so a Redis outage degrades to "unlimited", not
+/// "locked out").
+///
+/// Keyed on `real_ip`, not the request's `?user_id=`/`?plan=` query params:
+/// those are caller-supplied and unauthenticated at this layer (unlike
+/// `check_burst_rate`'s `user_id`, which the webhook path resolves
+/// server-side from Convex via the slug), so keying or tiering on them would
+/// let a caller mint an unlimited number of fresh budgets — or multiply
+/// their own — just by varying a query string. Always applies
+/// `search_rate_limit_free_per_min` for the same reason: there's no way to
+/// verify a claimed `plan` from this middleware (see
+/// `Config::search_rate_limit_pro_per_min`'s doc comment).
+pub async fn search_rate_limit(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = real_ip(&headers);
+    let key = format!(
+        "ratelimit:search:{}",
+        if ip.is_empty() { "unknown" } else { ip.as_str() }
+    );
+    let limit = state.config.load().search_rate_limit_free_per_min;
+
+    match state.redis.check_rate(&key, limit, RATE_LIMIT_WINDOW_SECS).await {
+        RateResult::Allowed => next.run(request).await,
+        RateResult::Denied { retry_after_ms } => {
+            let retry_after_secs = ((retry_after_ms + 999) / 1000).max(1);
+            let mut response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                axum::Json(serde_json::json!({"error": "rate limit exceeded"})),
+            )
+                .into_response();
+            if let Ok(value) = HeaderValue::from_str(&retry_after_secs.to_string()) {
+                response.headers_mut().insert("retry-after", value);
+            }
+            response
+        }
+    }
+}
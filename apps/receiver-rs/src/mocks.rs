@@ -0,0 +1,392 @@
+//! In-memory stand-ins for `RedisState`/`ConvexClient`, gated behind the
+//! `mocks` cargo feature so they never ship in a production build. Follows
+//! fred.rs's `mocks` feature: the same call surface the flush pipeline and
+//! cache warmer already depend on (`workers::flush::FlushRedisBackend`/
+//! `FlushConvexBackend`, `workers::cache_warmer::CacheWarmerRedisBackend`/
+//! `CacheWarmerConvexBackend`) gets a programmable fake implementation, so
+//! `drain_pass`, `fire_and_forget_clickhouse`, and `warm_caches` can be
+//! exercised deterministically in tests without a live Redis or Convex.
+//!
+//! Fault injection (`queue_capture_fault`, `force_circuit_open`,
+//! `set_response_delay`, `deny_lock`) lets a test assert re-enqueue-on-
+//! circuit-open, at-most-once delivery via `batch_id`-style dedup, and
+//! ClickHouse backpressure dropping without any networked fake.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::convex::client::ConvexError;
+use crate::convex::types::{BufferedRequest, CaptureResponse, EndpointInfo};
+use crate::workers::cache_warmer::{CacheWarmerConvexBackend, CacheWarmerRedisBackend};
+use crate::workers::flush::{FlushConvexBackend, FlushRedisBackend};
+
+/// A fault to inject in place of a programmed success response.
+#[derive(Debug, Clone, Copy)]
+pub enum ConvexFault {
+    CircuitOpen,
+    ServerError,
+    Network,
+    ClientError,
+}
+
+impl ConvexFault {
+    fn into_error(self, slug: &str) -> ConvexError {
+        match self {
+            ConvexFault::CircuitOpen => ConvexError::CircuitOpen,
+            ConvexFault::ServerError => {
+                ConvexError::ServerError(500, format!("mock: injected server error for {slug}"))
+            }
+            ConvexFault::Network => {
+                ConvexError::Network(format!("mock: injected network failure for {slug}"))
+            }
+            ConvexFault::ClientError => {
+                ConvexError::ClientError(400, format!("mock: injected client rejection for {slug}"))
+            }
+        }
+    }
+}
+
+enum CaptureOutcome {
+    Response(CaptureResponse),
+    Fault(ConvexFault),
+}
+
+#[derive(Default)]
+struct MockConvexState {
+    circuit_open: bool,
+    response_delay: Option<Duration>,
+    capture_queue: HashMap<String, VecDeque<CaptureOutcome>>,
+    capture_calls: Vec<(String, usize)>,
+    endpoint_results: HashMap<String, Result<Option<EndpointInfo>, ConvexFault>>,
+    quota_results: HashMap<String, Result<(), ConvexFault>>,
+}
+
+/// Programmable in-memory `ConvexClient` stand-in. Construct one, program it
+/// with `queue_capture_response`/`queue_capture_fault`/`force_circuit_open`,
+/// then pass `&mock` anywhere a `FlushConvexBackend`/`CacheWarmerConvexBackend`
+/// is expected.
+#[derive(Clone, Default)]
+pub struct MockConvexBackend {
+    state: Arc<Mutex<MockConvexState>>,
+}
+
+impl MockConvexBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Force `circuit_is_degraded()` to report open/closed, independent of
+    /// any queued `capture_batch` outcome.
+    pub fn force_circuit_open(&self, open: bool) {
+        self.state.lock().unwrap().circuit_open = open;
+    }
+
+    /// Delay every `capture_batch`/`fetch_and_cache_*` call by `delay`
+    /// before resolving, to simulate a slow Convex.
+    pub fn set_response_delay(&self, delay: Duration) {
+        self.state.lock().unwrap().response_delay = Some(delay);
+    }
+
+    /// Queue a `capture_batch` response for `slug`. Queued outcomes are
+    /// consumed FIFO; once the queue is empty, calls for that slug default
+    /// to a successful insert of the whole batch.
+    pub fn queue_capture_response(&self, slug: &str, response: CaptureResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .capture_queue
+            .entry(slug.to_string())
+            .or_default()
+            .push_back(CaptureOutcome::Response(response));
+    }
+
+    /// Queue a `capture_batch` failure for `slug` (see `ConvexFault`).
+    pub fn queue_capture_fault(&self, slug: &str, fault: ConvexFault) {
+        self.state
+            .lock()
+            .unwrap()
+            .capture_queue
+            .entry(slug.to_string())
+            .or_default()
+            .push_back(CaptureOutcome::Fault(fault));
+    }
+
+    pub fn set_endpoint_result(&self, slug: &str, result: Result<Option<EndpointInfo>, ConvexFault>) {
+        self.state
+            .lock()
+            .unwrap()
+            .endpoint_results
+            .insert(slug.to_string(), result);
+    }
+
+    pub fn set_quota_result(&self, slug: &str, result: Result<(), ConvexFault>) {
+        self.state
+            .lock()
+            .unwrap()
+            .quota_results
+            .insert(slug.to_string(), result);
+    }
+
+    /// Every `capture_batch` call observed so far, as `(slug, batch_len)` —
+    /// lets a test assert a retried batch was only ever sent once (at-most-
+    /// once) or was resent after a re-enqueue (at-least-once).
+    pub fn capture_calls(&self) -> Vec<(String, usize)> {
+        self.state.lock().unwrap().capture_calls.clone()
+    }
+
+    async fn maybe_delay(&self) {
+        let delay = self.state.lock().unwrap().response_delay;
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+impl FlushConvexBackend for MockConvexBackend {
+    fn circuit_is_degraded(&self) -> bool {
+        self.state.lock().unwrap().circuit_open
+    }
+
+    async fn capture_batch(
+        &self,
+        slug: &str,
+        requests: Vec<BufferedRequest>,
+    ) -> Result<CaptureResponse, ConvexError> {
+        self.maybe_delay().await;
+
+        let outcome = {
+            let mut state = self.state.lock().unwrap();
+            state.capture_calls.push((slug.to_string(), requests.len()));
+            state
+                .capture_queue
+                .get_mut(slug)
+                .and_then(VecDeque::pop_front)
+        };
+
+        match outcome {
+            Some(CaptureOutcome::Response(response)) => Ok(response),
+            Some(CaptureOutcome::Fault(fault)) => Err(fault.into_error(slug)),
+            None => Ok(CaptureResponse {
+                success: true,
+                error: String::new(),
+                inserted: requests.len(),
+                mock_response: None,
+                already_committed: false,
+            }),
+        }
+    }
+}
+
+impl CacheWarmerConvexBackend for MockConvexBackend {
+    fn circuit_is_degraded(&self) -> bool {
+        self.state.lock().unwrap().circuit_open
+    }
+
+    async fn fetch_and_cache_endpoint(&self, slug: &str) -> Result<Option<EndpointInfo>, ConvexError> {
+        self.maybe_delay().await;
+        match self.state.lock().unwrap().endpoint_results.get(slug) {
+            Some(Ok(info)) => Ok(info.clone()),
+            Some(Err(fault)) => Err(fault.into_error(slug)),
+            None => Ok(None),
+        }
+    }
+
+    async fn fetch_and_cache_quota(&self, slug: &str) -> Result<(), ConvexError> {
+        self.maybe_delay().await;
+        match self.state.lock().unwrap().quota_results.get(slug) {
+            Some(Ok(())) => Ok(()),
+            Some(Err(fault)) => Err(fault.into_error(slug)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct MockRedisState {
+    active_slugs: Vec<String>,
+    fresh_batches: HashMap<String, VecDeque<BufferedRequest>>,
+    due_retries: HashMap<String, VecDeque<BufferedRequest>>,
+    retry_pending_len: HashMap<String, usize>,
+    endpoints: HashMap<String, EndpointInfo>,
+    endpoint_ttls: HashMap<String, i64>,
+    quota_ttls: HashMap<String, i64>,
+    held_locks: HashSet<String>,
+    deny_locks: HashSet<String>,
+    requeued: Vec<(String, Vec<BufferedRequest>, String)>,
+    removed_active: Vec<String>,
+}
+
+/// Programmable in-memory `RedisState` stand-in covering the flush/cache-
+/// warmer surface. Construct one, seed it with `set_active_slugs`/
+/// `queue_batch`/`queue_due_retry`, then pass `&mock` anywhere a
+/// `FlushRedisBackend`/`CacheWarmerRedisBackend` is expected.
+#[derive(Clone, Default)]
+pub struct MockRedisBackend {
+    state: Arc<Mutex<MockRedisState>>,
+}
+
+impl MockRedisBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_active_slugs(&self, slugs: Vec<String>) {
+        self.state.lock().unwrap().active_slugs = slugs;
+    }
+
+    /// Queue fresh (not-yet-retried) requests for `take_batch` to hand out.
+    pub fn queue_batch(&self, slug: &str, requests: Vec<BufferedRequest>) {
+        self.state
+            .lock()
+            .unwrap()
+            .fresh_batches
+            .entry(slug.to_string())
+            .or_default()
+            .extend(requests);
+    }
+
+    /// Queue requests that `take_due_retries` should hand back immediately,
+    /// simulating a retry whose backoff has already elapsed.
+    pub fn queue_due_retry(&self, slug: &str, requests: Vec<BufferedRequest>) {
+        self.state
+            .lock()
+            .unwrap()
+            .due_retries
+            .entry(slug.to_string())
+            .or_default()
+            .extend(requests);
+    }
+
+    pub fn set_retry_pending_len(&self, slug: &str, len: usize) {
+        self.state
+            .lock()
+            .unwrap()
+            .retry_pending_len
+            .insert(slug.to_string(), len);
+    }
+
+    pub fn set_endpoint(&self, slug: &str, info: EndpointInfo) {
+        self.state.lock().unwrap().endpoints.insert(slug.to_string(), info);
+    }
+
+    pub fn set_endpoint_ttl(&self, slug: &str, ttl_secs: i64) {
+        self.state
+            .lock()
+            .unwrap()
+            .endpoint_ttls
+            .insert(slug.to_string(), ttl_secs);
+    }
+
+    pub fn set_quota_ttl(&self, slug: &str, ttl_secs: i64) {
+        self.state
+            .lock()
+            .unwrap()
+            .quota_ttls
+            .insert(slug.to_string(), ttl_secs);
+    }
+
+    /// Fault injection: make `try_lock(key, ..)` always return `None`, as if
+    /// another instance already held it.
+    pub fn deny_lock(&self, key: &str) {
+        self.state.lock().unwrap().deny_locks.insert(key.to_string());
+    }
+
+    /// Every `requeue` call observed so far, as `(slug, requests, last_error)`.
+    pub fn requeued_batches(&self) -> Vec<(String, Vec<BufferedRequest>, String)> {
+        self.state.lock().unwrap().requeued.clone()
+    }
+
+    /// Every slug dropped from the active set via `remove_active`.
+    pub fn removed_active_slugs(&self) -> Vec<String> {
+        self.state.lock().unwrap().removed_active.clone()
+    }
+}
+
+impl FlushRedisBackend for MockRedisBackend {
+    /// The real `RedisState` returns an opaque `LockGuard`; the mock just
+    /// hands back the lock key itself, since there's no TTL to race here.
+    type Lock = String;
+
+    async fn active_slugs(&self) -> Vec<String> {
+        self.state.lock().unwrap().active_slugs.clone()
+    }
+
+    async fn take_due_retries(&self, slug: &str, _now: i64) -> Vec<BufferedRequest> {
+        self.state
+            .lock()
+            .unwrap()
+            .due_retries
+            .remove(slug)
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+
+    async fn take_batch(&self, slug: &str, max: usize) -> Vec<BufferedRequest> {
+        let mut state = self.state.lock().unwrap();
+        let Some(queue) = state.fresh_batches.get_mut(slug) else {
+            return Vec::new();
+        };
+        queue.drain(..max.min(queue.len())).collect()
+    }
+
+    async fn retry_pending_len(&self, slug: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .retry_pending_len
+            .get(slug)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    async fn remove_active(&self, slug: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.active_slugs.retain(|s| s != slug);
+        state.removed_active.push(slug.to_string());
+    }
+
+    async fn requeue(
+        &self,
+        slug: &str,
+        requests: &[BufferedRequest],
+        last_error: &str,
+        _retry_cfg: crate::workers::flush::RetryConfig,
+    ) {
+        self.state.lock().unwrap().requeued.push((
+            slug.to_string(),
+            requests.to_vec(),
+            last_error.to_string(),
+        ));
+    }
+
+    async fn try_lock(&self, key: &str, _ttl_ms: u64) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+        if state.deny_locks.contains(key) || !state.held_locks.insert(key.to_string()) {
+            return None;
+        }
+        Some(key.to_string())
+    }
+
+    async fn release_lock(&self, guard: String) {
+        self.state.lock().unwrap().held_locks.remove(&guard);
+    }
+
+    async fn get_endpoint(&self, slug: &str) -> Option<EndpointInfo> {
+        self.state.lock().unwrap().endpoints.get(slug).cloned()
+    }
+}
+
+impl CacheWarmerRedisBackend for MockRedisBackend {
+    async fn active_slugs(&self) -> Vec<String> {
+        self.state.lock().unwrap().active_slugs.clone()
+    }
+
+    async fn endpoint_ttl(&self, slug: &str) -> Option<i64> {
+        self.state.lock().unwrap().endpoint_ttls.get(slug).copied()
+    }
+
+    async fn quota_ttl(&self, slug: &str) -> Option<i64> {
+        self.state.lock().unwrap().quota_ttls.get(slug).copied()
+    }
+}
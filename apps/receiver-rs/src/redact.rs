@@ -0,0 +1,242 @@
+//! Pre-capture redaction: rewrites sensitive values on a `BufferedRequest`
+//! (header denylist + JSON-pointer/body-substring body rules) to a fixed
+//! placeholder. Applied as a pure transform over the batch exactly once,
+//! inside `ConvexClient::capture_batch` just before `BatchPayload` is built —
+//! so it runs regardless of how a request made it into the flush buffer.
+//!
+//! Rules live on `EndpointInfo::redact_rules` (per-endpoint, like
+//! `crate::filter`'s accept/reject rules) and are parsed once per distinct
+//! raw text per slug via `RedactionCache`, mirroring `crate::filter::FilterCache`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::convex::types::BufferedRequest;
+
+pub const REDACTED_PLACEHOLDER: &str = "[REDACTED]";
+
+/// One redaction rule, one per line of `EndpointInfo::redact_rules`:
+///   `header:<name>`   — mask a header by name (case-insensitive)
+///   `json:<pointer>`  — mask a JSON body field by RFC 6901 pointer (e.g. `/user/ssn`)
+///   `body:<needle>`   — replace every occurrence of a literal substring in the body
+///
+/// `body:` is a literal-substring match rather than a real regex — consistent
+/// with the rest of this codebase's no-extra-dependency parsing (see the
+/// hand-rolled filter DSL and ClickHouse's `multiSearchAny` substring search).
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RedactionRule {
+    Header(String),
+    JsonPointer(String),
+    BodySubstring(String),
+}
+
+/// Parsed, ready-to-apply ruleset for one endpoint.
+#[derive(Debug, Clone, Default)]
+pub struct RedactionRuleSet {
+    rules: Vec<RedactionRule>,
+}
+
+impl RedactionRuleSet {
+    /// Parse newline-separated rule text. Blank lines and lines with an
+    /// unrecognized prefix are skipped with a warning rather than failing —
+    /// malformed config should redact less, not break capture.
+    pub fn parse(raw: &str) -> Self {
+        let mut rules = Vec::new();
+        for line in raw.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("header:") {
+                let name = name.trim();
+                if !name.is_empty() {
+                    rules.push(RedactionRule::Header(name.to_ascii_lowercase()));
+                }
+            } else if let Some(pointer) = line.strip_prefix("json:") {
+                let pointer = pointer.trim();
+                if !pointer.is_empty() {
+                    rules.push(RedactionRule::JsonPointer(pointer.to_string()));
+                }
+            } else if let Some(needle) = line.strip_prefix("body:") {
+                let needle = needle.trim();
+                if !needle.is_empty() {
+                    rules.push(RedactionRule::BodySubstring(needle.to_string()));
+                }
+            } else {
+                tracing::warn!(line, "unrecognized redaction rule, skipping");
+            }
+        }
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Apply every rule to a single request in place.
+    pub fn apply(&self, req: &mut BufferedRequest) {
+        if self.rules.is_empty() {
+            return;
+        }
+
+        let mut json_pointers = Vec::new();
+
+        for rule in &self.rules {
+            match rule {
+                RedactionRule::Header(name) => {
+                    for (key, value) in req.headers.iter_mut() {
+                        if key.eq_ignore_ascii_case(name) {
+                            value.clear();
+                            value.push_str(REDACTED_PLACEHOLDER);
+                        }
+                    }
+                }
+                RedactionRule::BodySubstring(needle) => {
+                    if req.body.contains(needle.as_str()) {
+                        req.body = req.body.replace(needle.as_str(), REDACTED_PLACEHOLDER);
+                    }
+                }
+                RedactionRule::JsonPointer(pointer) => json_pointers.push(pointer.as_str()),
+            }
+        }
+
+        if json_pointers.is_empty() || req.body.is_empty() {
+            return;
+        }
+
+        // Body rewrite only applies if it parses as JSON; a non-JSON body
+        // with json: rules configured is left untouched (header/body: rules
+        // still ran above).
+        if let Ok(mut value) = serde_json::from_str::<serde_json::Value>(&req.body) {
+            let mut changed = false;
+            for pointer in json_pointers {
+                if let Some(target) = value.pointer_mut(pointer) {
+                    *target = serde_json::Value::String(REDACTED_PLACEHOLDER.to_string());
+                    changed = true;
+                }
+            }
+            if changed
+                && let Ok(rewritten) = serde_json::to_string(&value)
+            {
+                req.body = rewritten;
+            }
+        }
+    }
+}
+
+/// Parse-once-per-distinct-rule-text cache, keyed by slug — same shape as
+/// `crate::filter::FilterCache`.
+#[derive(Clone, Default)]
+pub struct RedactionCache {
+    inner: Arc<RwLock<HashMap<String, (String, Arc<RedactionRuleSet>)>>>,
+}
+
+impl RedactionCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_or_parse(&self, slug: &str, raw: &str) -> Arc<RedactionRuleSet> {
+        if let Ok(cache) = self.inner.read()
+            && let Some((cached_raw, parsed)) = cache.get(slug)
+            && cached_raw == raw
+        {
+            return parsed.clone();
+        }
+
+        let parsed = Arc::new(RedactionRuleSet::parse(raw));
+
+        if let Ok(mut cache) = self.inner.write() {
+            cache.insert(slug.to_string(), (raw.to_string(), parsed.clone()));
+        }
+
+        parsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn req(body: &str, headers: &[(&str, &str)]) -> BufferedRequest {
+        BufferedRequest {
+            method: "POST".to_string(),
+            path: "/x".to_string(),
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            body: body.to_string(),
+            query_params: HashMap::new(),
+            ip: "127.0.0.1".to_string(),
+            received_at: 0,
+            attempts: 0,
+        }
+    }
+
+    #[test]
+    fn empty_ruleset_is_noop() {
+        let rules = RedactionRuleSet::parse("");
+        assert!(rules.is_empty());
+        let mut r = req("hello", &[("authorization", "Bearer secret")]);
+        rules.apply(&mut r);
+        assert_eq!(r.body, "hello");
+        assert_eq!(r.headers.get("authorization").unwrap(), "Bearer secret");
+    }
+
+    #[test]
+    fn header_rule_masks_case_insensitively() {
+        let rules = RedactionRuleSet::parse("header:Authorization");
+        let mut r = req("{}", &[("AUTHORIZATION", "Bearer secret")]);
+        rules.apply(&mut r);
+        assert_eq!(r.headers.get("AUTHORIZATION").unwrap(), REDACTED_PLACEHOLDER);
+    }
+
+    #[test]
+    fn body_substring_rule_replaces_all_occurrences() {
+        let rules = RedactionRuleSet::parse("body:4242424242424242");
+        let mut r = req("card=4242424242424242 retry=4242424242424242", &[]);
+        rules.apply(&mut r);
+        assert_eq!(
+            r.body,
+            format!("card={REDACTED_PLACEHOLDER} retry={REDACTED_PLACEHOLDER}")
+        );
+    }
+
+    #[test]
+    fn json_pointer_rule_masks_nested_field() {
+        let rules = RedactionRuleSet::parse("json:/user/ssn");
+        let mut r = req(r#"{"user":{"ssn":"123-45-6789","name":"Ann"}}"#, &[]);
+        rules.apply(&mut r);
+        let value: serde_json::Value = serde_json::from_str(&r.body).unwrap();
+        assert_eq!(value["user"]["ssn"], REDACTED_PLACEHOLDER);
+        assert_eq!(value["user"]["name"], "Ann");
+    }
+
+    #[test]
+    fn json_pointer_rule_leaves_non_json_body_untouched() {
+        let rules = RedactionRuleSet::parse("json:/ssn");
+        let mut r = req("not json at all", &[]);
+        rules.apply(&mut r);
+        assert_eq!(r.body, "not json at all");
+    }
+
+    #[test]
+    fn unrecognized_lines_are_skipped_not_fatal() {
+        let rules = RedactionRuleSet::parse("bogus:whatever\nheader:x-api-key");
+        let mut r = req("{}", &[("x-api-key", "sekret")]);
+        rules.apply(&mut r);
+        assert_eq!(r.headers.get("x-api-key").unwrap(), REDACTED_PLACEHOLDER);
+    }
+
+    #[test]
+    fn cache_reparses_only_when_raw_text_changes() {
+        let cache = RedactionCache::new();
+        let first = cache.get_or_parse("demo", "header:authorization");
+        let second = cache.get_or_parse("demo", "header:authorization");
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let third = cache.get_or_parse("demo", "header:cookie");
+        assert!(!Arc::ptr_eq(&first, &third));
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use sha2::{Digest, Sha256};
 
 use super::RedisState;
@@ -12,7 +14,12 @@ impl RedisState {
     /// Check whether this request is a duplicate. Returns `true` if the request
     /// should be processed (first seen), `false` if it's a duplicate.
     ///
-    /// Uses Redis SET NX EX for atomic check-and-set with TTL.
+    /// Consults the in-process `local_dedup` L1 cache first — same-instance
+    /// Cloudflare edge retries land here within sub-millisecond windows, so
+    /// this is the common case and skips the Redis round trip entirely. A
+    /// miss falls through to Redis's `SET NX EX`, which stays the
+    /// authoritative check across instances.
+    ///
     /// The fingerprint is: slug + method + path + body (first 512 bytes) + client IP.
     pub async fn check_dedup(
         &self,
@@ -40,6 +47,15 @@ impl RedisState {
         let hash: String = hash_bytes.iter().map(|b| format!("{b:02x}")).collect();
         let key = format!("dedup:{slug}:{hash}");
 
+        {
+            let mut local = self.local_dedup.lock().unwrap();
+            local.poll_expired();
+            if local.contains(&key) {
+                return false;
+            }
+            local.insert(key.clone(), Duration::from_secs(DEDUP_TTL_SECS));
+        }
+
         let mut conn = self.conn.clone();
         // SET key "" NX EX 2 â€” returns true if key was set (first seen)
         let result: Result<bool, _> = redis::cmd("SET")
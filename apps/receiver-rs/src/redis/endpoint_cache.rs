@@ -22,7 +22,8 @@ impl RedisState {
             return;
         };
         let mut conn = self.conn.clone();
-        let _: Result<(), _> = conn.set_ex(&key, &json, self.endpoint_ttl_secs).await;
+        let ttl_secs = self.config.load().endpoint_cache_ttl_secs;
+        let _: Result<(), _> = conn.set_ex(&key, &json, ttl_secs).await;
     }
 
     /// Evict cached endpoint info (called on cache invalidation).
@@ -0,0 +1,36 @@
+use redis::AsyncCommands;
+
+use super::RedisState;
+
+const KEY_PREFIX: &str = "filt:rejected:";
+/// How long a per-slug rejection counter lives without being hit again.
+const STATS_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+impl RedisState {
+    /// Bump the rejection counter for a slug so operators can see how often
+    /// its filter rules are dropping traffic (surfaced via `get_filter_rejection_count`).
+    pub async fn record_filter_rejection(&self, slug: &str) {
+        let key = format!("{KEY_PREFIX}{slug}");
+        let mut conn = self.conn.clone();
+        let result: Result<(), _> = redis::pipe()
+            .incr(&key, 1)
+            .ignore()
+            .expire(&key, STATS_TTL_SECS as i64)
+            .ignore()
+            .query_async(&mut conn)
+            .await;
+
+        if let Err(e) = result {
+            tracing::warn!(slug, error = %e, "failed to record filter rejection stat");
+        }
+    }
+
+    /// Total requests rejected by this slug's filter rules since the counter
+    /// last rolled over (see `STATS_TTL_SECS`).
+    pub async fn get_filter_rejection_count(&self, slug: &str) -> u64 {
+        let key = format!("{KEY_PREFIX}{slug}");
+        let mut conn = self.conn.clone();
+        let count: Result<Option<u64>, _> = conn.get(&key).await;
+        count.ok().flatten().unwrap_or(0)
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::time::Duration;
+
+use tokio_util::time::DelayQueue;
+use tokio_util::time::delay_queue::Key;
+
+/// A set of items that auto-expire after a fixed TTL, backed by a
+/// `DelayQueue` for O(1) expiry bookkeeping instead of scanning the whole map
+/// on every insert. Models the `hashset_delay` structure from the
+/// 0g-storage tree. Used by `RedisState::check_dedup` as an in-process L1
+/// cache in front of the authoritative Redis dedup check.
+pub struct HashSetDelay<T> {
+    queue: DelayQueue<T>,
+    keys: HashMap<T, Key>,
+}
+
+impl<T: Eq + Hash + Clone> HashSetDelay<T> {
+    pub fn new() -> Self {
+        Self {
+            queue: DelayQueue::new(),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Insert `item`, expiring after `ttl`. A no-op if already present — the
+    /// existing entry's expiry is left as-is rather than refreshed.
+    pub fn insert(&mut self, item: T, ttl: Duration) {
+        if self.keys.contains_key(&item) {
+            return;
+        }
+        let key = self.queue.insert(item.clone(), ttl);
+        self.keys.insert(item, key);
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.keys.contains_key(item)
+    }
+
+    /// Remove every entry whose TTL has elapsed. `DelayQueue` only evicts
+    /// lazily on poll, so callers must drive this periodically (here, on
+    /// every `check_dedup` call) to keep the map from growing unbounded.
+    pub fn poll_expired(&mut self) {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        while let Poll::Ready(Some(Ok(expired))) = self.queue.poll_expired(&mut cx) {
+            self.keys.remove(expired.get_ref());
+        }
+    }
+}
+
+/// A waker that does nothing. `DelayQueue::poll_expired` takes a `Context`
+/// because it's designed to be awaited in a task, but we only ever drain it
+/// synchronously right before an `insert` — never actually parking to be
+/// woken on expiry.
+fn noop_waker() -> Waker {
+    fn no_op(_: *const ()) {}
+    fn clone(_: *const ()) -> RawWaker {
+        raw_waker()
+    }
+    fn raw_waker() -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, no_op, no_op, no_op);
+    unsafe { Waker::from_raw(raw_waker()) }
+}
@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use super::RedisState;
+
+/// Lua script for a compare-and-delete unlock: only release the lock if it's
+/// still held by the token that acquired it, so a worker whose TTL already
+/// expired (and whose key some other instance has since re-acquired) can't
+/// delete someone else's lock out from under them.
+const UNLOCK_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// A held distributed lock, returned by `RedisState::try_lock`. Must be
+/// passed to `RedisState::release_lock` to unlock early; otherwise it
+/// expires on its own after the requested TTL.
+pub struct LockGuard {
+    key: String,
+    token: String,
+}
+
+impl RedisState {
+    /// Acquire a Redlock-style single-instance lock on `key` for `ttl_ms`,
+    /// via `SET key token NX PX ttl`. Returns `None` if someone else already
+    /// holds it (or on a Redis error — fail closed, since the caller uses
+    /// this to avoid double-processing, not just for best-effort fairness).
+    pub async fn try_lock(&self, key: &str, ttl_ms: u64) -> Option<LockGuard> {
+        let mut conn = self.conn.clone();
+        let token = random_token();
+
+        let result: Result<Option<String>, _> = redis::cmd("SET")
+            .arg(key)
+            .arg(&token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async(&mut conn)
+            .await;
+
+        match result {
+            Ok(Some(_)) => Some(LockGuard {
+                key: key.to_string(),
+                token,
+            }),
+            Ok(None) => None,
+            Err(e) => {
+                tracing::warn!(key, error = %e, "lock acquisition failed, treating as held");
+                None
+            }
+        }
+    }
+
+    /// Release a lock acquired via `try_lock`, but only if it's still ours —
+    /// a compare-and-delete so a lock we held past its TTL (and that another
+    /// instance has since acquired) isn't deleted out from under them.
+    pub async fn release_lock(&self, guard: LockGuard) {
+        let mut conn = self.conn.clone();
+        let result: Result<i64, _> = redis::Script::new(UNLOCK_SCRIPT)
+            .key(&guard.key)
+            .arg(&guard.token)
+            .invoke_async(&mut conn)
+            .await;
+
+        if let Err(e) = result {
+            tracing::warn!(key = guard.key, error = %e, "lock release failed");
+        }
+    }
+}
+
+/// Hash-based pseudo-random owner token — same approach as
+/// `convex::client`'s jitter RNG, to avoid pulling in a `rand`/`uuid`
+/// dependency just to tell lock owners apart.
+fn random_token() -> String {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    static TOKEN_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let seed = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+        ^ TOKEN_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
@@ -1,30 +1,76 @@
 pub mod dedup;
 pub mod endpoint_cache;
+pub mod filter_stats;
+pub mod local_dedup;
+pub mod lock;
 pub mod quota;
+pub mod rate_limit;
 pub mod request_buffer;
+pub mod search_cache;
+pub mod stream;
+
+use std::sync::{Arc, Mutex};
 
 use redis::aio::ConnectionManager;
 
+use crate::config::SharedConfig;
+use crate::spill::SpillStore;
+use local_dedup::HashSetDelay;
+use search_cache::LocalSearchCache;
+
 /// Shared Redis state passed to handlers via Axum State.
 #[derive(Clone)]
 pub struct RedisState {
     pub conn: ConnectionManager,
-    pub endpoint_ttl_secs: u64,
-    pub quota_ttl_secs: u64,
+    /// Held alongside `conn` so `stream::subscribe_stream` can open a
+    /// dedicated pub/sub connection per live-tail subscriber — `conn`'s
+    /// `ConnectionManager` multiplexes regular commands and can't carry a
+    /// `SUBSCRIBE`.
+    client: redis::Client,
+    /// Read on every TTL-dependent call so `ENDPOINT_CACHE_TTL_SECS`/
+    /// `QUOTA_CACHE_TTL_SECS` pick up hot config reloads immediately.
+    pub config: SharedConfig,
+    /// Local disk fallback `push_request` spills to when Redis itself is
+    /// unreachable — see `crate::spill` and `workers::spill_reconciler`.
+    pub spill: SpillStore,
+    /// In-process L1 cache of recently-seen dedup fingerprints, consulted
+    /// before the authoritative Redis check — see `dedup::check_dedup`.
+    /// Shared (not re-created) across clones of `RedisState` so every
+    /// worker/handler on this instance sees the same recent-request window.
+    local_dedup: Arc<Mutex<HashSetDelay<String>>>,
+    /// In-process L1 tier of cached `/search` response bodies, consulted
+    /// before the shared Redis tier — see `search_cache::get_cached_search`.
+    /// `mini_moka::sync::Cache` is already internally sharded/cloneable, so
+    /// (unlike `local_dedup`) it doesn't need its own `Arc<Mutex<_>>`.
+    local_search_cache: LocalSearchCache,
 }
 
 impl RedisState {
     pub async fn new(
         redis_url: &str,
-        endpoint_ttl_secs: u64,
-        quota_ttl_secs: u64,
+        config: SharedConfig,
+        spill: SpillStore,
     ) -> Result<Self, redis::RedisError> {
         let client = redis::Client::open(redis_url)?;
-        let conn = ConnectionManager::new(client).await?;
+        let conn = ConnectionManager::new(client.clone()).await?;
+        let local_search_cache = search_cache::new_local_cache(config.load().search_cache_ttl_secs);
         Ok(Self {
             conn,
-            endpoint_ttl_secs,
-            quota_ttl_secs,
+            client,
+            config,
+            spill,
+            local_dedup: Arc::new(Mutex::new(HashSetDelay::new())),
+            local_search_cache,
         })
     }
+
+    /// Check Redis is reachable. Used by `workers::spill_reconciler` to
+    /// decide when it's safe to start draining the disk spill store back in.
+    pub async fn ping(&self) -> bool {
+        let mut conn = self.conn.clone();
+        redis::cmd("PING")
+            .query_async::<String>(&mut conn)
+            .await
+            .is_ok()
+    }
 }
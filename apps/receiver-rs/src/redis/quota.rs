@@ -61,12 +61,14 @@ impl RedisState {
             .invoke_async(&mut conn)
             .await;
 
-        match result {
+        let outcome = match result {
             Ok(1) => QuotaResult::Allowed,
             Ok(0) => QuotaResult::Exceeded,
             Ok(-1) => QuotaResult::NotFound,
             _ => QuotaResult::NotFound, // Redis error -> triggers blocking Convex fetch
-        }
+        };
+        crate::metrics::record_quota_check(&outcome);
+        outcome
     }
 
     /// Set quota data in Redis.
@@ -84,6 +86,7 @@ impl RedisState {
     ) {
         let unlimited_str = if is_unlimited { "1" } else { "0" };
         let mut conn = self.conn.clone();
+        let ttl_secs = self.config.load().quota_cache_ttl_secs;
 
         if !user_id.is_empty() {
             // Per-user quota key (shared across all user's endpoints)
@@ -98,7 +101,7 @@ impl RedisState {
                 .arg(period_end)
                 .arg(unlimited_str)
                 .arg(user_id)
-                .arg(self.quota_ttl_secs)
+                .arg(ttl_secs)
                 .invoke_async(&mut conn)
                 .await;
 
@@ -111,7 +114,7 @@ impl RedisState {
             let _: Result<(), _> = redis::pipe()
                 .hset(&slug_key, "userId", user_id)
                 .ignore()
-                .expire(&slug_key, self.quota_ttl_secs as i64)
+                .expire(&slug_key, ttl_secs as i64)
                 .ignore()
                 .query_async(&mut conn)
                 .await;
@@ -127,7 +130,7 @@ impl RedisState {
                 .arg(period_end)
                 .arg(unlimited_str)
                 .arg("") // empty userId for ephemeral
-                .arg(self.quota_ttl_secs)
+                .arg(ttl_secs)
                 .invoke_async(&mut conn)
                 .await;
 
@@ -0,0 +1,98 @@
+use crate::convex::types::now_ms;
+
+use super::RedisState;
+
+const USER_PREFIX: &str = "rate:user:";
+const SLUG_PREFIX: &str = "rate:slug:";
+
+/// Result of an atomic sliding-window rate check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateResult {
+    /// Request is allowed; a timestamp entry was recorded (except when the
+    /// Redis call failed and `check_rate` failed open, which records nothing).
+    Allowed,
+    /// Request is denied; the oldest entry in the window ages out in
+    /// `retry_after_ms`.
+    Denied { retry_after_ms: i64 },
+}
+
+/// Sliding-window-log rate limiter, atomic via a Lua script.
+/// KEYS[1] = the window's sorted set, KEYS[2] = a per-key sequence counter
+/// (gives each entry a unique ZADD member even when several land in the same
+/// millisecond, which a plain `now_ms` member would collide on).
+/// ARGV[1] = now_ms, ARGV[2] = window_ms, ARGV[3] = limit
+/// Returns: {1, 0} if allowed, {0, retry_after_ms} if denied.
+const RATE_LIMIT_SCRIPT: &str = r#"
+local key = KEYS[1]
+local seq_key = KEYS[2]
+local now_ms = tonumber(ARGV[1])
+local window_ms = tonumber(ARGV[2])
+local limit = tonumber(ARGV[3])
+
+redis.call('ZREMRANGEBYSCORE', key, '-inf', now_ms - window_ms)
+local count = redis.call('ZCARD', key)
+
+if count < limit then
+    local seq = redis.call('INCR', seq_key)
+    redis.call('PEXPIRE', seq_key, window_ms + 1000)
+    redis.call('ZADD', key, now_ms, now_ms .. ':' .. seq)
+    redis.call('PEXPIRE', key, window_ms)
+    return {1, 0}
+end
+
+local oldest = redis.call('ZRANGE', key, 0, 0, 'WITHSCORES')
+local retry_after_ms = 0
+if oldest[2] ~= nil then
+    retry_after_ms = (tonumber(oldest[2]) + window_ms) - now_ms
+    if retry_after_ms < 0 then
+        retry_after_ms = 0
+    end
+end
+return {0, retry_after_ms}
+"#;
+
+impl RedisState {
+    /// Atomic sliding-window-log rate check against an arbitrary key: drops
+    /// entries older than `now - window_secs`, counts what's left, and either
+    /// admits the request (recording a new timestamp entry) or denies it with
+    /// how long until the oldest entry ages out of the window.
+    pub async fn check_rate(&self, key: &str, limit: u64, window_secs: u64) -> RateResult {
+        let window_ms = (window_secs * 1000) as i64;
+        let now = now_ms();
+        let seq_key = format!("{key}:seq");
+        let mut conn = self.conn.clone();
+
+        let result: Result<(i64, i64), _> = redis::Script::new(RATE_LIMIT_SCRIPT)
+            .key(key)
+            .key(&seq_key)
+            .arg(now)
+            .arg(window_ms)
+            .arg(limit as i64)
+            .invoke_async(&mut conn)
+            .await;
+
+        match result {
+            Ok((1, _)) => RateResult::Allowed,
+            Ok((_, retry_after_ms)) => RateResult::Denied {
+                retry_after_ms: retry_after_ms.max(0),
+            },
+            Err(e) => {
+                tracing::warn!(key, error = %e, "rate limiter Redis error, failing open");
+                RateResult::Allowed
+            }
+        }
+    }
+
+    /// Burst rate limit for a webhook request, keyed the same way as
+    /// `check_quota`: per-user (shared across all the user's endpoints) when
+    /// a userId is present, per-slug for ephemeral endpoints.
+    pub async fn check_burst_rate(&self, slug: &str, user_id: Option<&str>) -> RateResult {
+        let key = match user_id {
+            Some(uid) if !uid.is_empty() => format!("{USER_PREFIX}{uid}"),
+            _ => format!("{SLUG_PREFIX}{slug}"),
+        };
+        let config = self.config.load();
+        self.check_rate(&key, config.rate_limit_max, config.rate_limit_window_secs)
+            .await
+    }
+}
@@ -1,10 +1,61 @@
 use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
 
 use super::RedisState;
-use crate::convex::types::BufferedRequest;
+use crate::convex::types::{now_ms, BufferedRequest};
 
 const BUF_PREFIX: &str = "buf:";
 const ACTIVE_SET: &str = "buf:active";
+const RETRY_PREFIX: &str = "buf:retry:";
+const DEAD_PREFIX: &str = "buf:dead:";
+
+/// A request that exhausted `buffer_retry_max_attempts` and was moved out of
+/// the retry queue for later inspection rather than retried or dropped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterEntry {
+    pub request: BufferedRequest,
+    pub error: String,
+    pub failed_at: i64,
+}
+
+/// Outcome of `RedisState::push_request`, for `handlers::webhook` to decide
+/// what to tell the sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    /// Went straight into the Redis buffer, as usual.
+    Buffered,
+    /// Redis was unreachable, so the request was parked on local disk (see
+    /// `crate::spill`) for `workers::spill_reconciler` to replay later.
+    Spilled,
+    /// Redis was unreachable AND the disk spill store is already at its
+    /// configured cap — the request was not recorded anywhere and the caller
+    /// must not tell the sender it was accepted.
+    Rejected,
+}
+
+/// Lua script to atomically pull due entries out of the retry sorted set.
+/// KEYS[1] = `buf:retry:{slug}`, ARGV[1] = now_ms.
+/// Returns the JSON-encoded requests whose visible-at score has elapsed.
+const TAKE_DUE_RETRIES_SCRIPT: &str = r#"
+local items = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1])
+if #items > 0 then
+    redis.call('ZREM', KEYS[1], unpack(items))
+end
+return items
+"#;
+
+/// Lua script to atomically take up to N items from the head of a list
+/// (oldest first — dead letters are appended with `RPUSH`, unlike the
+/// `buf:` lists which use `LPUSH`).
+const DRAIN_HEAD_SCRIPT: &str = r#"
+local count = tonumber(ARGV[1])
+local len = redis.call('LLEN', KEYS[1])
+if len == 0 then return {} end
+local take = math.min(count, len)
+local items = redis.call('LRANGE', KEYS[1], 0, take - 1)
+redis.call('LTRIM', KEYS[1], take, -1)
+return items
+"#;
 
 /// Lua script to atomically take up to N items from the tail of a list.
 /// Returns the items taken (FIFO order: oldest first).
@@ -23,12 +74,15 @@ return items
 "#;
 
 impl RedisState {
-    /// Push a buffered request and mark the slug as active.
-    pub async fn push_request(&self, slug: &str, req: &BufferedRequest) {
+    /// Push a buffered request and mark the slug as active. Falls back to the
+    /// local disk spill store (`crate::spill`) if the Redis pipeline fails,
+    /// unless the spill store is already at `spill_max_entries` — in which
+    /// case the request is dropped and the caller must not acknowledge it.
+    pub async fn push_request(&self, slug: &str, req: &BufferedRequest) -> PushOutcome {
         let key = format!("{BUF_PREFIX}{slug}");
         let Ok(json) = serde_json::to_string(req) else {
             tracing::warn!(slug, "failed to serialize buffered request");
-            return;
+            return PushOutcome::Rejected;
         };
 
         let mut conn = self.conn.clone();
@@ -40,8 +94,26 @@ impl RedisState {
             .query_async(&mut conn)
             .await;
 
-        if let Err(e) = result {
-            tracing::warn!(slug, error = %e, "failed to push request to Redis buffer");
+        match result {
+            Ok(()) => {
+                crate::metrics::add_buffer_depth(slug, 1);
+                PushOutcome::Buffered
+            }
+            Err(e) => {
+                let cap = self.config.load().spill_max_entries;
+                if self.spill.spill_len() >= cap {
+                    tracing::error!(
+                        slug,
+                        error = %e,
+                        cap,
+                        "Redis push failed and disk spill store is at capacity, dropping request"
+                    );
+                    return PushOutcome::Rejected;
+                }
+                tracing::warn!(slug, error = %e, "failed to push request to Redis, spilling to disk");
+                self.spill.append(slug, req);
+                PushOutcome::Spilled
+            }
         }
     }
 
@@ -93,10 +165,17 @@ impl RedisState {
             .await;
 
         match result {
-            Ok(items) => items
-                .iter()
-                .filter_map(|s| serde_json::from_str(s).ok())
-                .collect(),
+            Ok(items) => {
+                let taken: Vec<BufferedRequest> = items
+                    .iter()
+                    .filter_map(|s| serde_json::from_str(s).ok())
+                    .collect();
+                if !taken.is_empty() {
+                    crate::metrics::add_buffer_depth(slug, -(taken.len() as i64));
+                    crate::metrics::record_batch_size(taken.len());
+                }
+                taken
+            }
             Err(e) => {
                 tracing::warn!(slug, error = %e, "failed to take batch from Redis");
                 Vec::new()
@@ -110,29 +189,150 @@ impl RedisState {
         let _: Result<(), _> = conn.srem(ACTIVE_SET, slug).await;
     }
 
-    /// Re-enqueue requests that failed to flush (push back to tail for retry).
-    /// Uses a pipeline so the re-enqueue is all-or-nothing.
-    pub async fn requeue(&self, slug: &str, requests: &[BufferedRequest]) {
-        let key = format!("{BUF_PREFIX}{slug}");
+    /// Re-enqueue requests that failed to flush, modeled on the
+    /// background-jobs retry pattern: each request's `attempts` is
+    /// incremented and, if it's still at or under `max_attempts`, the request is
+    /// written into `buf:retry:{slug}` (a sorted set scored by the next
+    /// visible-at timestamp, computed as exponential backoff off
+    /// `base_delay_ms`). Requests whose `attempts` exceed `max_attempts` are moved to
+    /// `buf:dead:{slug}` instead, along with `last_error`, so a permanently
+    /// failing request (e.g. a ClickHouse schema mismatch) stops looping and
+    /// blocking progress.
+    pub async fn requeue(
+        &self,
+        slug: &str,
+        requests: &[BufferedRequest],
+        last_error: &str,
+        max_attempts: u32,
+        base_delay_ms: u64,
+        cap_ms: u64,
+    ) {
+        let retry_key = format!("{RETRY_PREFIX}{slug}");
+        let dead_key = format!("{DEAD_PREFIX}{slug}");
+        let now = now_ms();
         let mut conn = self.conn.clone();
         let mut pipe = redis::pipe();
+        let mut any_retry = false;
 
         for req in requests {
-            let Ok(json) = serde_json::to_string(req) else {
+            let mut req = req.clone();
+            req.attempts += 1;
+
+            if req.attempts > max_attempts {
+                let entry = DeadLetterEntry {
+                    request: req,
+                    error: last_error.to_string(),
+                    failed_at: now,
+                };
+                if let Ok(json) = serde_json::to_string(&entry) {
+                    pipe.rpush(&dead_key, json).ignore();
+                }
+                continue;
+            }
+
+            let delay_ms = base_delay_ms
+                .saturating_mul(1u64 << req.attempts.min(32))
+                .min(cap_ms);
+            let visible_at = now + delay_ms as i64;
+
+            let Ok(json) = serde_json::to_string(&req) else {
                 continue;
             };
-            pipe.rpush(&key, json).ignore();
+            pipe.zadd(&retry_key, json, visible_at).ignore();
+            any_retry = true;
+        }
+
+        if any_retry {
+            pipe.sadd(ACTIVE_SET, slug).ignore();
         }
-        pipe.sadd(ACTIVE_SET, slug).ignore();
 
         let _: Result<(), _> = pipe.query_async(&mut conn).await;
     }
 
+    /// Atomically pull requests out of a slug's retry queue whose backoff has
+    /// elapsed (visible-at <= `now`), for folding back into the normal flush
+    /// path alongside fresh `take_batch` items.
+    pub async fn take_due_retries(&self, slug: &str, now: i64) -> Vec<BufferedRequest> {
+        let key = format!("{RETRY_PREFIX}{slug}");
+        let mut conn = self.conn.clone();
+
+        let result: Result<Vec<String>, _> = redis::Script::new(TAKE_DUE_RETRIES_SCRIPT)
+            .key(&key)
+            .arg(now)
+            .invoke_async(&mut conn)
+            .await;
+
+        match result {
+            Ok(items) => items
+                .iter()
+                .filter_map(|s| serde_json::from_str(s).ok())
+                .collect(),
+            Err(e) => {
+                tracing::warn!(slug, error = %e, "failed to take due retries from Redis");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Count of requests still waiting in a slug's retry queue (due or not).
+    /// Used by the drain loop to decide whether a slug with an empty `buf:`
+    /// list can be dropped from the active set, or whether it still has
+    /// delayed retries pending.
+    pub async fn retry_pending_len(&self, slug: &str) -> usize {
+        let key = format!("{RETRY_PREFIX}{slug}");
+        let mut conn = self.conn.clone();
+        let len: Result<usize, _> = conn.zcard(&key).await;
+        len.unwrap_or(0)
+    }
+
+    /// Number of entries in a slug's dead-letter list.
+    pub async fn dead_letter_len(&self, slug: &str) -> usize {
+        let key = format!("{DEAD_PREFIX}{slug}");
+        let mut conn = self.conn.clone();
+        let len: Result<usize, _> = conn.llen(&key).await;
+        len.unwrap_or(0)
+    }
+
+    /// Drain up to `max` entries from a slug's dead-letter list (oldest
+    /// first), for surfacing via an admin endpoint or manual inspection.
+    pub async fn drain_dead_letters(&self, slug: &str, max: usize) -> Vec<DeadLetterEntry> {
+        let key = format!("{DEAD_PREFIX}{slug}");
+        let mut conn = self.conn.clone();
+
+        let result: Result<Vec<String>, _> = redis::Script::new(DRAIN_HEAD_SCRIPT)
+            .key(&key)
+            .arg(max)
+            .invoke_async(&mut conn)
+            .await;
+
+        match result {
+            Ok(items) => items
+                .iter()
+                .filter_map(|s| serde_json::from_str(s).ok())
+                .collect(),
+            Err(e) => {
+                tracing::warn!(slug, error = %e, "failed to drain dead letters from Redis");
+                Vec::new()
+            }
+        }
+    }
+
     /// Get the length of a slug's request buffer.
     pub async fn buffer_len(&self, slug: &str) -> usize {
         let key = format!("{BUF_PREFIX}{slug}");
         let mut conn = self.conn.clone();
-        let len: Result<usize, _> = conn.llen(&key).await;
-        len.unwrap_or(0)
+        let len: usize = conn.llen(&key).await.unwrap_or(0);
+        crate::metrics::set_buffer_depth(slug, len);
+        len
+    }
+
+    /// Sum of `buffer_len` across every active slug. Used by the shutdown
+    /// coordinator to report how much work is left to drain.
+    pub async fn total_buffered_len(&self) -> usize {
+        let mut total = 0;
+        for slug in self.active_slugs().await {
+            total += self.buffer_len(&slug).await;
+        }
+        total
     }
 }
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use mini_moka::sync::Cache;
+use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
+
+use super::RedisState;
+use crate::storage::SearchQuery;
+use crate::time::TimestampFormat;
+
+const KEY_PREFIX: &str = "search:";
+const SLUG_INDEX_PREFIX: &str = "search:idx:";
+/// Bucket untargeted by a slug (a user searching across all of their
+/// endpoints) — not a real slug, so `evict_search_cache_for_slug` never
+/// touches it; it only ever expires via TTL.
+const UNSCOPED_BUCKET: &str = "_all";
+/// Bounds the process-local tier's memory footprint — an eviction here just
+/// means the next lookup falls through to the Redis tier, so a modest size
+/// is fine (the Redis tier is the tier invalidation actually relies on).
+const LOCAL_CACHE_MAX_ENTRIES: u64 = 10_000;
+
+pub type LocalSearchCache = Cache<String, Arc<str>>;
+
+/// Build the process-local L1 tier — `RedisState::new` constructs one of
+/// these once at startup from `Config::search_cache_ttl_secs`, which is why
+/// that field is a cold (restart-only) one: moka bakes its TTL into the
+/// builder, unlike the Redis tier's `SET EX` which reads the TTL fresh on
+/// every write.
+pub fn new_local_cache(ttl_secs: u64) -> LocalSearchCache {
+    Cache::builder()
+        .max_capacity(LOCAL_CACHE_MAX_ENTRIES)
+        .time_to_live(Duration::from_secs(ttl_secs))
+        // Needed for `evict_search_cache_for_slug`'s `invalidate_entries_if`.
+        .support_invalidation_closures()
+        .build()
+}
+
+/// Cache key for a normalized `SearchQuery` + database name + output format —
+/// SHA-256 over the already-normalized fields (null-byte separated, same
+/// convention as `SearchResultRequest::from_row`'s synthetic row id) so the
+/// key is stable regardless of how the caller ordered its query string.
+/// `format` must be folded in here: the cached value is the fully-rendered
+/// response body (`SearchResultRequest::to_json(format)`), and two callers
+/// asking for the same filters with different `received_at` formats must not
+/// collide on the same entry.
+fn search_cache_key(query: &SearchQuery, db: &str, format: TimestampFormat) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(query.user_id.as_bytes());
+    hasher.update(b"\x00");
+    hasher.update(query.plan.as_deref().unwrap_or("").as_bytes());
+    hasher.update(b"\x00");
+    hasher.update(query.slug.as_deref().unwrap_or("").as_bytes());
+    hasher.update(b"\x00");
+    hasher.update(query.method.as_deref().unwrap_or("").as_bytes());
+    hasher.update(b"\x00");
+    hasher.update(query.q.as_deref().unwrap_or("").as_bytes());
+    hasher.update(b"\x00");
+    hasher.update(query.from.unwrap_or(0).to_le_bytes());
+    hasher.update(query.to.unwrap_or(0).to_le_bytes());
+    hasher.update(query.limit.to_le_bytes());
+    hasher.update(query.offset.to_le_bytes());
+    hasher.update([query.order_desc as u8]);
+    hasher.update(query.after.map(|c| c.encode()).unwrap_or_default().as_bytes());
+    hasher.update(b"\x00");
+    hasher.update(db.as_bytes());
+    hasher.update(b"\x00");
+    hasher.update([format as u8]);
+
+    let digest = hasher.finalize();
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The slug bucket a cached entry is filed under, for scoped invalidation —
+/// `_all` when the query wasn't narrowed to one slug.
+fn slug_bucket(query: &SearchQuery) -> &str {
+    query.slug.as_deref().unwrap_or(UNSCOPED_BUCKET)
+}
+
+impl RedisState {
+    /// Check the local `mini_moka` tier, then the shared Redis tier, for a
+    /// cached `/search` response body. `None` on a miss in both tiers — the
+    /// caller runs the real query and calls `set_cached_search`.
+    pub async fn get_cached_search(
+        &self,
+        query: &SearchQuery,
+        db: &str,
+        format: TimestampFormat,
+    ) -> Option<Arc<str>> {
+        let key = search_cache_key(query, db, format);
+
+        if let Some(hit) = self.local_search_cache.get(&key) {
+            return Some(hit);
+        }
+
+        let mut conn = self.conn.clone();
+        let raw: Option<String> = conn.get(format!("{KEY_PREFIX}{key}")).await.ok()?;
+        let body: Arc<str> = raw?.into();
+        self.local_search_cache.insert(key, body.clone());
+        Some(body)
+    }
+
+    /// Store a `/search` response body in both tiers, and record the cache
+    /// key under the query's slug bucket so `evict_search_cache_for_slug`
+    /// can find it later without a Redis `SCAN`. A single pipeline batches
+    /// the value write, its index membership, and both TTLs.
+    pub async fn set_cached_search(
+        &self,
+        query: &SearchQuery,
+        db: &str,
+        format: TimestampFormat,
+        body: &str,
+    ) {
+        let key = search_cache_key(query, db, format);
+        let ttl_secs = self.config.load().search_cache_ttl_secs as i64;
+        let redis_key = format!("{KEY_PREFIX}{key}");
+        let index_key = format!("{SLUG_INDEX_PREFIX}{}", slug_bucket(query));
+
+        self.local_search_cache.insert(key, body.into());
+
+        let mut conn = self.conn.clone();
+        let _: Result<(), _> = redis::pipe()
+            .set_ex(&redis_key, body, ttl_secs.max(1) as u64)
+            .ignore()
+            .sadd(&index_key, &redis_key)
+            .ignore()
+            .expire(&index_key, ttl_secs.max(1))
+            .ignore()
+            .query_async(&mut conn)
+            .await;
+    }
+
+    /// Purge every cached `/search` entry scoped to `slug` — called from
+    /// `/internal/cache-invalidate/{slug}` alongside the endpoint/quota
+    /// evictions so a config change doesn't keep serving a stale search page
+    /// for up to `search_cache_ttl_secs`.
+    pub async fn evict_search_cache_for_slug(&self, slug: &str) {
+        let index_key = format!("{SLUG_INDEX_PREFIX}{slug}");
+        let mut conn = self.conn.clone();
+
+        let members: Vec<String> = conn.smembers(&index_key).await.unwrap_or_default();
+        if members.is_empty() {
+            let _: Result<(), _> = conn.del(&index_key).await;
+            return;
+        }
+
+        // The local tier's cache key is a hash, so it carries no slug we
+        // could match on — invalidate the whole local tier rather than
+        // leave it serving a stale entry. Invalidation is rare (an operator
+        // action, not a hot path) and the tier is just an optimization on
+        // top of the Redis tier's TTL, so over-invalidating here is cheap
+        // and still correct.
+        let _ = self.local_search_cache.invalidate_entries_if(|_, _| true);
+
+        let mut pipe = redis::pipe();
+        for member in &members {
+            pipe.del(member).ignore();
+        }
+        pipe.del(&index_key).ignore();
+        let _: Result<(), _> = pipe.query_async(&mut conn).await;
+    }
+}
@@ -0,0 +1,34 @@
+use super::RedisState;
+
+/// The pub/sub channel a slug's live-tail events are published to — see
+/// `handlers::stream::stream_webhook`.
+fn stream_channel(slug: &str) -> String {
+    format!("stream:{slug}")
+}
+
+impl RedisState {
+    /// Publish a captured request to `slug`'s live-tail channel. Best-effort:
+    /// a publish failure (or zero subscribers) doesn't affect delivery, so
+    /// errors are logged and swallowed rather than surfaced to the caller.
+    pub async fn publish_stream_event(&self, slug: &str, payload: &str) {
+        let mut conn = self.conn.clone();
+        let result: redis::RedisResult<i64> = redis::cmd("PUBLISH")
+            .arg(stream_channel(slug))
+            .arg(payload)
+            .query_async(&mut conn)
+            .await;
+
+        if let Err(e) = result {
+            tracing::warn!(slug, error = %e, "failed to publish request-stream event");
+        }
+    }
+
+    /// Open a dedicated pub/sub connection — `ConnectionManager` multiplexes
+    /// regular commands but can't carry a `SUBSCRIBE`, so live-tail needs its
+    /// own connection per subscriber — and subscribe to `slug`'s channel.
+    pub async fn subscribe_stream(&self, slug: &str) -> redis::RedisResult<redis::aio::PubSub> {
+        let mut pubsub = self.client.get_async_pubsub().await?;
+        pubsub.subscribe(stream_channel(slug)).await?;
+        Ok(pubsub)
+    }
+}
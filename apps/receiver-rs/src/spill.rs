@@ -0,0 +1,185 @@
+//! Local durable fallback for `RedisState::push_request` when Redis itself is
+//! unreachable. Without this, a request that arrives while Redis is down (or
+//! whose push pipeline errors) is acknowledged to the sender with a 200 and
+//! then lost, since the only copy of it ever lived in an in-flight Redis
+//! command. Modeled on relay's move to an embedded sled store: the request is
+//! appended to an on-disk tree instead, and `workers::spill_reconciler`
+//! drains it back into Redis once `RedisState::ping` succeeds again.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use sled::Tree;
+
+use crate::convex::types::BufferedRequest;
+
+/// Disk-backed queue of requests that couldn't be pushed to Redis. Keyed
+/// `{slug}:{monotonic_seq:020}` — zero-padded so sled's natural key-byte
+/// order is FIFO within a slug, which lets the reconciler drain oldest-first
+/// per slug with a plain tree scan instead of tracking per-slug cursors.
+#[derive(Clone)]
+pub struct SpillStore {
+    tree: Tree,
+    /// Mirrors the tree's entry count. `sled::Tree::len()` is a full-tree
+    /// scan (documented O(n)), and `push_request` checks `spill_len()` on
+    /// every spilled request on the async webhook-handling hot path — under
+    /// a sustained Redis outage that's O(n^2) total work blocking tokio
+    /// worker threads with synchronous disk I/O exactly when the system is
+    /// most loaded. Kept in sync in `append`/`drain_oldest` instead.
+    len: Arc<AtomicUsize>,
+}
+
+impl SpillStore {
+    /// Open (or create) the spill store at `path`. Called once at startup,
+    /// like `ClickHouseClient::new` — the sled handle is held for the life
+    /// of the process. The one `tree.len()` scan here is a one-time startup
+    /// cost, not a per-request one.
+    pub fn open(path: &str) -> Result<Self, sled::Error> {
+        let db = sled::open(path)?;
+        let tree = db.open_tree("spill")?;
+        let len = Arc::new(AtomicUsize::new(tree.len()));
+        Ok(Self { tree, len })
+    }
+
+    /// Append one request to disk for `slug`. Logs and drops on I/O failure —
+    /// the Redis push this backstops already failed, so there's nowhere
+    /// further to fall back to.
+    pub fn append(&self, slug: &str, req: &BufferedRequest) {
+        let Ok(json) = serde_json::to_vec(req) else {
+            tracing::warn!(slug, "failed to serialize request for disk spill");
+            return;
+        };
+
+        let seq = match self.tree.generate_id() {
+            Ok(seq) => seq,
+            Err(e) => {
+                tracing::error!(slug, error = %e, "failed to allocate spill sequence number");
+                return;
+            }
+        };
+
+        if let Err(e) = self.tree.insert(spill_key(slug, seq), json) {
+            tracing::error!(slug, error = %e, "failed to spill request to disk");
+            return;
+        }
+
+        self.len.fetch_add(1, Ordering::Relaxed);
+        crate::metrics::set_spill_depth(self.spill_len());
+    }
+
+    /// Number of requests currently parked on disk, across all slugs. Reads
+    /// the in-memory counter kept in sync by `append`/`drain_oldest` rather
+    /// than scanning the tree — see the `len` field's doc comment.
+    pub fn spill_len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Drain up to `max` of the oldest spilled requests, grouped by slug in
+    /// the order their keys sort (FIFO within a slug), removing each drained
+    /// key from disk. Returns `(slug, requests)` pairs ready to hand to
+    /// `RedisState::requeue`.
+    pub fn drain_oldest(&self, max: usize) -> Vec<(String, Vec<BufferedRequest>)> {
+        let mut groups: Vec<(String, Vec<BufferedRequest>)> = Vec::new();
+        let mut drained_keys = Vec::new();
+
+        for item in self.tree.iter().take(max) {
+            let Ok((key, value)) = item else { continue };
+            let Some(slug) = slug_from_key(&key) else {
+                drained_keys.push(key);
+                continue;
+            };
+            let Ok(req) = serde_json::from_slice::<BufferedRequest>(&value) else {
+                drained_keys.push(key);
+                continue;
+            };
+
+            match groups.last_mut() {
+                Some((last_slug, reqs)) if *last_slug == slug => reqs.push(req),
+                _ => groups.push((slug, vec![req])),
+            }
+            drained_keys.push(key);
+        }
+
+        for key in &drained_keys {
+            let _ = self.tree.remove(key);
+        }
+        if !drained_keys.is_empty() {
+            self.len.fetch_sub(drained_keys.len(), Ordering::Relaxed);
+            crate::metrics::set_spill_depth(self.spill_len());
+        }
+
+        groups
+    }
+}
+
+fn spill_key(slug: &str, seq: u64) -> Vec<u8> {
+    format!("{slug}:{seq:020}").into_bytes()
+}
+
+fn slug_from_key(key: &[u8]) -> Option<String> {
+    let key = std::str::from_utf8(key).ok()?;
+    key.rsplit_once(':').map(|(slug, _seq)| slug.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convex::types::now_ms;
+
+    fn sample_request(path: &str) -> BufferedRequest {
+        BufferedRequest {
+            method: "POST".to_string(),
+            path: path.to_string(),
+            headers: Default::default(),
+            body: "{}".to_string(),
+            query_params: Default::default(),
+            ip: "127.0.0.1".to_string(),
+            received_at: now_ms(),
+            attempts: 0,
+        }
+    }
+
+    fn temp_store() -> SpillStore {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("spill-test-{}-{n}", std::process::id()));
+        SpillStore::open(dir.to_str().expect("utf8 path")).expect("open spill store")
+    }
+
+    #[test]
+    fn append_and_drain_round_trips_in_fifo_order() {
+        let store = temp_store();
+        store.append("acme", &sample_request("/a"));
+        store.append("acme", &sample_request("/b"));
+        store.append("other", &sample_request("/c"));
+
+        assert_eq!(store.spill_len(), 3);
+
+        let groups = store.drain_oldest(10);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "acme");
+        assert_eq!(groups[0].1.len(), 2);
+        assert_eq!(groups[0].1[0].path, "/a");
+        assert_eq!(groups[0].1[1].path, "/b");
+        assert_eq!(groups[1].0, "other");
+
+        assert_eq!(store.spill_len(), 0);
+    }
+
+    #[test]
+    fn drain_oldest_respects_max() {
+        let store = temp_store();
+        for i in 0..5 {
+            store.append("acme", &sample_request(&format!("/{i}")));
+        }
+
+        let groups = store.drain_oldest(2);
+        assert_eq!(groups.iter().map(|(_, r)| r.len()).sum::<usize>(), 2);
+        assert_eq!(store.spill_len(), 3);
+    }
+}
@@ -0,0 +1,428 @@
+//! Backend-neutral storage abstraction for captured requests.
+//!
+//! `AppState::storage` holds an `Arc<dyn StorageBackend>` so `handlers::search`
+//! (and `handlers::facets`) can query without knowing whether the rows live
+//! in ClickHouse, Postgres, or SQLite — mirrors how atuin splits its server
+//! into `atuin-server-database` plus a swappable Postgres/SQLite
+//! implementation. Every other cross-backend trait in this codebase (see
+//! `workers::flush::FlushRedisBackend`) is a generic bound instead of a trait
+//! object, because those only ever have two concrete callers known at compile
+//! time — the real backend and a test mock. Here the backend is chosen once
+//! at startup from config, so the call site needs dynamic dispatch, which
+//! means the trait method returns a boxed future instead of using `async fn`
+//! directly (AFIT isn't object-safe).
+//!
+//! Scoped to `search` only — insert-on-flush and the retention sweep stay on
+//! the concrete `ClickHouseClient` (`workers::flush`, `workers::clickhouse_retention`),
+//! since both already have exactly the two call sites (real backend, test
+//! mock) that `FlushRedisBackend`'s generic-bound style above fits, not a
+//! trait object with a single runtime caller. An earlier revision of this
+//! trait also declared `insert_requests`/`ping`/`retention_sweep`, but nothing
+//! ever called them through `dyn StorageBackend` — dead trait surface, since
+//! removed. `MetadataStore` is the matching seam for Convex's endpoint/quota
+//! lookups, implemented here for symmetry but likewise not yet threaded
+//! through `AppState::convex`.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::clickhouse::client::{ClickHouseClient, escape_clickhouse_identifier, escape_clickhouse_string};
+use crate::clickhouse::query::{PageCursor, ROW_HASH_EXPR};
+use crate::clickhouse::types::SearchResultRequest;
+use crate::convex::client::{ConvexClient, ConvexError};
+use crate::convex::types::EndpointInfo;
+use crate::handlers::webhook::is_valid_slug;
+use crate::time::epoch_ms_to_ch_decimal;
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Backend-neutral search parameters — built from `handlers::search::SearchParams`
+/// (or any future caller) once limit/offset/order have been normalized.
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub user_id: String,
+    pub plan: Option<String>,
+    pub slug: Option<String>,
+    pub method: Option<String>,
+    pub q: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: u32,
+    pub offset: u32,
+    pub order_desc: bool,
+    /// Keyset cursor from a previous page's `nextCursor` (see
+    /// `clickhouse::query::PageCursor`). When set, `build_search_page_sql`
+    /// replaces `offset` with a `WHERE (received_at, row_hash) < cursor`
+    /// cutoff, same as `clickhouse::query::build_request_page_sql` does for
+    /// `handlers::endpoint_search` — `offset` is kept only for callers that
+    /// haven't switched over yet.
+    pub after: Option<PageCursor>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchQueryError {
+    InvalidPlan,
+    InvalidSlug,
+    InvalidCursor,
+}
+
+/// Error from `StorageBackend::search` — distinguishes a bad query (400) from
+/// a backend failure (500), matching the 400-vs-500 split `handlers::search`
+/// makes between a `SearchQueryError` and a raw query error string.
+#[derive(Debug)]
+pub enum SearchError {
+    InvalidQuery(SearchQueryError),
+    Backend(String),
+}
+
+/// A storage backend for captured webhook requests, scoped to what the
+/// search API actually calls through `dyn StorageBackend` today — see the
+/// module doc comment for why insert-on-flush and retention sweeps stay on
+/// the concrete `ClickHouseClient` instead of living here.
+pub trait StorageBackend: Send + Sync {
+    /// Returns the matching page alongside a `nextCursor` token (see
+    /// `SearchQuery::after`) — `None` once the last page has been reached,
+    /// or always `None` when `query.after` is unset and the offset path ran.
+    fn search<'a>(
+        &'a self,
+        query: &'a SearchQuery,
+    ) -> BoxFuture<'a, Result<(Vec<SearchResultRequest>, Option<String>), SearchError>>;
+}
+
+impl StorageBackend for ClickHouseClient {
+    fn search<'a>(
+        &'a self,
+        query: &'a SearchQuery,
+    ) -> BoxFuture<'a, Result<(Vec<SearchResultRequest>, Option<String>), SearchError>> {
+        Box::pin(async move {
+            if query.after.is_some() {
+                let limit = query.limit.clamp(1, 200) as usize;
+                let sql = build_search_page_sql(query, self.database()).map_err(SearchError::InvalidQuery)?;
+                ClickHouseClient::query_page(self, &sql, limit)
+                    .await
+                    .map_err(SearchError::Backend)
+            } else {
+                let sql = build_search_sql(query, self.database()).map_err(SearchError::InvalidQuery)?;
+                let rows = ClickHouseClient::query_requests(self, &sql)
+                    .await
+                    .map_err(SearchError::Backend)?;
+                Ok((rows, None))
+            }
+        })
+    }
+}
+
+/// The slice of Convex's endpoint/quota lookups a backend-agnostic caller
+/// would need — the metadata-store half of the split described in chunk5-1.
+/// See the module doc comment for why this isn't yet threaded through
+/// `AppState::convex`.
+pub trait MetadataStore: Send + Sync {
+    fn fetch_endpoint<'a>(&'a self, slug: &'a str) -> BoxFuture<'a, Result<Option<EndpointInfo>, ConvexError>>;
+
+    fn check_quota<'a>(&'a self, slug: &'a str) -> BoxFuture<'a, Result<(), ConvexError>>;
+}
+
+impl MetadataStore for ConvexClient {
+    fn fetch_endpoint<'a>(&'a self, slug: &'a str) -> BoxFuture<'a, Result<Option<EndpointInfo>, ConvexError>> {
+        Box::pin(async move { ConvexClient::fetch_and_cache_endpoint(self, slug).await })
+    }
+
+    fn check_quota<'a>(&'a self, slug: &'a str) -> BoxFuture<'a, Result<(), ConvexError>> {
+        Box::pin(async move { ConvexClient::fetch_and_cache_quota(self, slug).await })
+    }
+}
+
+fn free_retention_clause_for_plan(plan: Option<&str>) -> Result<Option<&'static str>, SearchQueryError> {
+    match plan {
+        Some("free") => Ok(Some("received_at >= now() - INTERVAL 7 DAY")),
+        Some("pro") | None => Ok(None),
+        Some(_) => Err(SearchQueryError::InvalidPlan),
+    }
+}
+
+/// Build the `WHERE` conditions shared by every ClickHouse query over
+/// `requests` — `build_search_sql` below and `handlers::facets`'s aggregate
+/// queries both filter the same rows, just project/group them differently.
+pub(crate) fn build_where_clause(query: &SearchQuery) -> Result<String, SearchQueryError> {
+    let mut conditions = vec![format!("user_id = '{}'", escape_clickhouse_string(&query.user_id))];
+
+    match free_retention_clause_for_plan(query.plan.as_deref()) {
+        Ok(Some(clause)) => conditions.push(clause.to_string()),
+        Ok(None) => {}
+        Err(err) => return Err(err),
+    }
+
+    if let Some(slug) = &query.slug {
+        if !is_valid_slug(slug) {
+            return Err(SearchQueryError::InvalidSlug);
+        }
+        conditions.push(format!("slug = '{}'", escape_clickhouse_string(slug)));
+    }
+
+    if let Some(method) = &query.method
+        && method != "ALL"
+    {
+        conditions.push(format!("method = '{}'", escape_clickhouse_string(method)));
+    }
+
+    // Use multiSearchAny() for substring search — it does exact substring
+    // matching (no wildcard/regex escaping needed) and is supported by
+    // ngrambf_v1 skip indexes for efficient filtering.
+    if let Some(q) = &query.q
+        && !q.is_empty()
+    {
+        let escaped = escape_clickhouse_string(q);
+        conditions.push(format!(
+            "(multiSearchAny(path, ['{escaped}']) OR multiSearchAny(body, ['{escaped}']) OR multiSearchAny(headers, ['{escaped}']))"
+        ));
+    }
+
+    // Use integer arithmetic for timestamps to avoid f64 precision loss
+    // and potential scientific notation formatting.
+    if let Some(from) = query.from {
+        let secs = from.div_euclid(1000);
+        let ms = from.rem_euclid(1000) as u64;
+        conditions.push(format!("received_at >= toDateTime64('{secs}.{ms:03}', 3, 'UTC')"));
+    }
+
+    if let Some(to) = query.to {
+        let secs = to.div_euclid(1000);
+        let ms = to.rem_euclid(1000) as u64;
+        conditions.push(format!("received_at <= toDateTime64('{secs}.{ms:03}', 3, 'UTC')"));
+    }
+
+    Ok(conditions.join(" AND "))
+}
+
+/// Build the ClickHouse SQL for a `SearchQuery`. Moved here from
+/// `handlers::search` — this is now the ClickHouse-specific implementation
+/// detail behind `StorageBackend::search`, not something the handler builds
+/// directly.
+fn build_search_sql(query: &SearchQuery, db: &str) -> Result<String, SearchQueryError> {
+    let limit = query.limit.min(200);
+    let offset = query.offset.min(10_000);
+    let order = if query.order_desc { "DESC" } else { "ASC" };
+
+    let where_clause = build_where_clause(query)?;
+    let db = escape_clickhouse_identifier(db);
+
+    Ok(format!(
+        "SELECT endpoint_id, slug, user_id, method, path, headers, body, query_params, ip, content_type, size, is_ephemeral, received_at \
+         FROM `{db}`.`requests` \
+         WHERE {where_clause} \
+         ORDER BY received_at {order} \
+         LIMIT {limit} OFFSET {offset}"
+    ))
+}
+
+/// Build a keyset-paginated search query: same filters as `build_search_sql`,
+/// but ordered by `(received_at, row_hash)` and cut off with a `query.after`
+/// comparison instead of `OFFSET` — the DESC/ASC comparator flips with
+/// `query.order_desc` so a caller paging "oldest first" still moves forward.
+/// Requests `limit + 1` rows, matching `clickhouse::query::build_request_page_sql`'s
+/// convention: `ClickHouseClient::query_page` uses the extra row to decide
+/// whether a next cursor exists, then trims it off.
+fn build_search_page_sql(query: &SearchQuery, db: &str) -> Result<String, SearchQueryError> {
+    let limit = query.limit.clamp(1, 200);
+    let order = if query.order_desc { "DESC" } else { "ASC" };
+    let cmp = if query.order_desc { "<" } else { ">" };
+
+    let mut where_clause = build_where_clause(query)?;
+
+    if let Some(cursor) = &query.after {
+        let decimal = epoch_ms_to_ch_decimal(cursor.received_at_ms);
+        where_clause.push_str(&format!(
+            " AND (received_at, {ROW_HASH_EXPR}) {cmp} (toDateTime64('{decimal}', 3, 'UTC'), {})",
+            cursor.row_hash
+        ));
+    }
+
+    let db = escape_clickhouse_identifier(db);
+
+    Ok(format!(
+        "SELECT endpoint_id, slug, user_id, method, path, headers, body, query_params, ip, content_type, size, is_ephemeral, received_at, {ROW_HASH_EXPR} AS row_hash \
+         FROM `{db}`.`requests` \
+         WHERE {where_clause} \
+         ORDER BY received_at {order}, row_hash {order} \
+         LIMIT {}",
+        limit + 1
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        SearchQuery, SearchQueryError, build_search_page_sql, build_search_sql, free_retention_clause_for_plan,
+    };
+    use crate::clickhouse::query::PageCursor;
+
+    fn query(overrides: impl FnOnce(&mut SearchQuery)) -> SearchQuery {
+        let mut q = SearchQuery {
+            user_id: "user_123".to_string(),
+            plan: None,
+            slug: None,
+            method: None,
+            q: None,
+            from: None,
+            to: None,
+            limit: 50,
+            offset: 0,
+            order_desc: true,
+            after: None,
+        };
+        overrides(&mut q);
+        q
+    }
+
+    #[test]
+    fn free_plan_gets_retention_clause() {
+        let clause = free_retention_clause_for_plan(Some("free")).expect("free plan should be valid");
+        assert_eq!(clause, Some("received_at >= now() - INTERVAL 7 DAY"));
+    }
+
+    #[test]
+    fn pro_and_missing_plan_have_no_clause() {
+        let pro_clause = free_retention_clause_for_plan(Some("pro")).expect("pro plan should be valid");
+        assert_eq!(pro_clause, None);
+
+        let none_clause = free_retention_clause_for_plan(None).expect("missing plan should be valid");
+        assert_eq!(none_clause, None);
+    }
+
+    #[test]
+    fn invalid_plan_is_rejected() {
+        let result = free_retention_clause_for_plan(Some("enterprise"));
+        assert_eq!(result, Err(SearchQueryError::InvalidPlan));
+    }
+
+    #[test]
+    fn build_search_sql_includes_free_plan_retention_clause() {
+        let q = query(|q| {
+            q.plan = Some("free".to_string());
+            q.slug = Some("demo_slug".to_string());
+            q.method = Some("POST".to_string());
+            q.limit = 25;
+            q.offset = 10;
+        });
+
+        let sql = build_search_sql(&q, "webhooks").expect("sql should build");
+
+        assert!(sql.contains("FROM `webhooks`.`requests`"));
+        assert!(sql.contains("user_id = 'user_123'"));
+        assert!(sql.contains("received_at >= now() - INTERVAL 7 DAY"));
+        assert!(sql.contains("slug = 'demo_slug'"));
+        assert!(sql.contains("method = 'POST'"));
+        assert!(sql.contains("LIMIT 25 OFFSET 10"));
+    }
+
+    #[test]
+    fn build_search_sql_omits_retention_for_pro_plan() {
+        let q = query(|q| q.plan = Some("pro".to_string()));
+        let sql = build_search_sql(&q, "webhooks").expect("sql should build");
+        assert!(!sql.contains("INTERVAL 7 DAY"));
+    }
+
+    #[test]
+    fn build_search_sql_rejects_invalid_slug() {
+        let q = query(|q| {
+            q.plan = Some("free".to_string());
+            q.slug = Some("../bad".to_string());
+        });
+
+        let err = build_search_sql(&q, "webhooks").expect_err("invalid slug should fail");
+        assert_eq!(err, SearchQueryError::InvalidSlug);
+    }
+
+    #[test]
+    fn build_search_sql_escapes_inputs_and_handles_negative_timestamps() {
+        let q = query(|q| {
+            q.user_id = "user'; DROP TABLE requests--".to_string();
+            q.q = Some("needle'\\\\test".to_string());
+            q.from = Some(-1);
+            q.to = Some(-1001);
+            q.order_desc = false;
+        });
+
+        let sql = build_search_sql(&q, "web`hooks").expect("sql should build");
+
+        assert!(sql.contains("FROM `web``hooks`.`requests`"));
+        assert!(sql.contains("user_id = 'user\\'; DROP TABLE requests--'"));
+        assert!(sql.contains("multiSearchAny(path, ['needle\\'\\\\\\\\test'])"));
+        assert!(sql.contains("received_at >= toDateTime64('-1.999', 3, 'UTC')"));
+        assert!(sql.contains("received_at <= toDateTime64('-2.999', 3, 'UTC')"));
+        assert!(sql.contains("ORDER BY received_at ASC"));
+    }
+
+    #[test]
+    fn build_search_page_sql_requests_one_extra_row_and_orders_by_row_hash() {
+        let q = query(|q| q.limit = 50);
+        let sql = build_search_page_sql(&q, "webhooks").expect("sql should build");
+
+        assert!(sql.contains("cityHash64(method, path, headers, body, query_params, ip) AS row_hash"));
+        assert!(sql.contains("ORDER BY received_at DESC, row_hash DESC"));
+        assert!(sql.contains("LIMIT 51"));
+        assert!(!sql.contains("OFFSET"));
+    }
+
+    #[test]
+    fn build_search_page_sql_cuts_off_with_desc_comparator_by_default() {
+        let cursor = PageCursor {
+            received_at_ms: 1_739_800_496_789,
+            row_hash: 42,
+        };
+        let q = query(|q| q.after = Some(cursor));
+        let sql = build_search_page_sql(&q, "webhooks").expect("sql should build");
+
+        assert!(sql.contains(
+            "AND (received_at, cityHash64(method, path, headers, body, query_params, ip)) < (toDateTime64('1739800496.789', 3, 'UTC'), 42)"
+        ));
+    }
+
+    #[test]
+    fn build_search_page_sql_flips_to_gt_comparator_for_ascending_order() {
+        let cursor = PageCursor {
+            received_at_ms: 1_739_800_496_789,
+            row_hash: 42,
+        };
+        let q = query(|q| {
+            q.order_desc = false;
+            q.after = Some(cursor);
+        });
+        let sql = build_search_page_sql(&q, "webhooks").expect("sql should build");
+
+        assert!(sql.contains("ORDER BY received_at ASC, row_hash ASC"));
+        assert!(sql.contains(
+            "AND (received_at, cityHash64(method, path, headers, body, query_params, ip)) > (toDateTime64('1739800496.789', 3, 'UTC'), 42)"
+        ));
+    }
+
+    #[test]
+    fn build_search_page_sql_ties_on_identical_received_at_break_on_row_hash() {
+        // Two rows sharing `received_at` can only be told apart by `row_hash`
+        // in the `WHERE` cutoff — assert the tuple comparison (not a plain
+        // `received_at <` check) is what's emitted, so equal timestamps with
+        // a higher row_hash are still excluded from the next page.
+        let cursor = PageCursor {
+            received_at_ms: 1_000,
+            row_hash: 7,
+        };
+        let q = query(|q| q.after = Some(cursor));
+        let sql = build_search_page_sql(&q, "webhooks").expect("sql should build");
+
+        assert!(sql.contains("(received_at, cityHash64(method, path, headers, body, query_params, ip)) < (toDateTime64('1.000', 3, 'UTC'), 7)"));
+    }
+
+    #[test]
+    fn build_search_page_sql_clamps_limit() {
+        let q = query(|q| q.limit = 10_000);
+        let sql = build_search_page_sql(&q, "webhooks").expect("sql should build");
+        assert!(sql.contains("LIMIT 201"));
+    }
+
+    #[test]
+    fn build_search_page_sql_rejects_invalid_slug() {
+        let q = query(|q| q.slug = Some("../bad".to_string()));
+        let err = build_search_page_sql(&q, "webhooks").expect_err("invalid slug should fail");
+        assert_eq!(err, SearchQueryError::InvalidSlug);
+    }
+}
@@ -0,0 +1,262 @@
+//! Chrono-free timestamp parsing/formatting shared by the ClickHouse layer
+//! and the search API's `received_at` output-format option.
+//!
+//! Civil-date <-> epoch-day conversion uses Howard Hinnant's well-known
+//! `days_from_civil`/`civil_from_days` algorithm (proleptic Gregorian,
+//! correct for any year including negative/pre-1970 ones — no year-by-year
+//! loop, so leap days and far-past/future dates fall out for free).
+
+/// Parse epoch milliseconds from a ClickHouse `DateTime64(3)` JSON value.
+/// Accepts either its usual decimal-epoch-seconds form (e.g. `"1739800496.789"`)
+/// or, via [`parse_rfc3339_ms`], an RFC3339/ISO-8601 string — ClickHouse never
+/// emits the latter today, but accepting it keeps this function usable
+/// anywhere a "received_at"-shaped string shows up. Returns `0.0` and logs on
+/// anything unparseable, matching the rest of this layer's defensive parsing.
+pub(crate) fn parse_received_at(s: &str) -> f64 {
+    if let Ok(f) = s.parse::<f64>()
+        && f > 946_684_800.0
+        && f < 4_102_444_800.0
+    {
+        return f * 1000.0;
+    }
+    match parse_rfc3339_ms(s) {
+        Some(ms) => ms as f64,
+        None => {
+            tracing::warn!(value = s, "failed to parse received_at timestamp");
+            0.0
+        }
+    }
+}
+
+/// Parse an RFC3339/ISO-8601 datetime into epoch milliseconds (UTC).
+/// Accepts an optional `T` or space date/time separator, 0-9 fractional
+/// second digits (truncated to millis), and an optional trailing `Z` or
+/// `±HH:MM`/`±HHMM` offset (defaults to UTC when absent).
+pub fn parse_rfc3339_ms(s: &str) -> Option<i64> {
+    if s.len() < 19 {
+        return None;
+    }
+    let epoch_secs = parse_datetime_to_epoch(&s[..19])?;
+    let mut rest = &s[19..];
+
+    let mut millis: i64 = 0;
+    if let Some(frac) = rest.strip_prefix('.') {
+        let digits_len = frac.bytes().take_while(u8::is_ascii_digit).count();
+        let digits = &frac[..digits_len];
+        if !digits.is_empty() {
+            let truncated = &digits[..digits.len().min(3)];
+            let value: i64 = truncated.parse().ok()?;
+            millis = value * 10i64.pow((3 - truncated.len()) as u32);
+        }
+        rest = &frac[digits_len..];
+    }
+
+    let offset_secs = if rest.is_empty() || rest.eq_ignore_ascii_case("z") {
+        0
+    } else {
+        parse_offset(rest)?
+    };
+
+    Some((epoch_secs - offset_secs) * 1000 + millis)
+}
+
+/// Parse a `±HH:MM` or `±HHMM` UTC offset into seconds east of UTC.
+fn parse_offset(s: &str) -> Option<i64> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1i64, &s[1..]),
+        b'-' => (-1i64, &s[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if digits.len() != 4 {
+        return None;
+    }
+    let hours: i64 = digits[0..2].parse().ok()?;
+    let mins: i64 = digits[2..4].parse().ok()?;
+    if hours > 23 || mins > 59 {
+        return None;
+    }
+    Some(sign * (hours * 3600 + mins * 60))
+}
+
+/// Parse a fixed-width `YYYY-MM-DDtHH:MM:SS` core (separator at byte 10 can
+/// be anything, e.g. `T` or a space) to epoch seconds (UTC).
+fn parse_datetime_to_epoch(s: &str) -> Option<i64> {
+    if s.len() < 19 {
+        return None;
+    }
+
+    let year: i64 = s[0..4].parse().ok()?;
+    let month: i64 = s[5..7].parse().ok()?;
+    let day: i64 = s[8..10].parse().ok()?;
+    let hour: i64 = s[11..13].parse().ok()?;
+    let min: i64 = s[14..16].parse().ok()?;
+    let sec: i64 = s[17..19].parse().ok()?;
+
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || !(0..=23).contains(&hour)
+        || !(0..=59).contains(&min)
+        || !(0..=59).contains(&sec)
+    {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 86400 + hour * 3600 + min * 60 + sec)
+}
+
+/// Render epoch milliseconds as a ClickHouse `DateTime64(3)`-compatible
+/// decimal-epoch-seconds string (e.g. `"1739800496.789"`). Uses
+/// div_euclid/rem_euclid for correct handling of negative timestamps.
+pub(crate) fn epoch_ms_to_ch_decimal(ms: i64) -> String {
+    let secs = ms.div_euclid(1000);
+    let subsec_ms = ms.rem_euclid(1000) as u64;
+    format!("{secs}.{subsec_ms:03}")
+}
+
+/// Render epoch milliseconds as a full RFC3339 UTC string
+/// (`"2026-02-17T12:34:56.789Z"`), for the `received_at=rfc3339` search
+/// output format.
+pub fn epoch_ms_to_rfc3339(ms: i64) -> String {
+    let secs = ms.div_euclid(1000);
+    let subsec_ms = ms.rem_euclid(1000) as u64;
+    let days = secs.div_euclid(86400);
+    let secs_of_day = secs.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    let hour = secs_of_day / 3600;
+    let min = (secs_of_day % 3600) / 60;
+    let sec = secs_of_day % 60;
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{min:02}:{sec:02}.{subsec_ms:03}Z")
+}
+
+/// Days since 1970-01-01 for a proleptic-Gregorian civil date. Negative for
+/// dates before the epoch. Howard Hinnant's `days_from_civil`.
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400; // [0, 399]
+    let mp = (m + 9) % 12; // [0, 11] (Mar=0 .. Feb=11)
+    let doy = (153 * mp + 2) / 5 + d - 1; // [0, 365]
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy; // [0, 146096]
+    era * 146097 + doe - 719468
+}
+
+/// Inverse of [`days_from_civil`]: epoch day -> (year, month, day).
+fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097; // [0, 146096]
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365; // [0, 399]
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100); // [0, 365]
+    let mp = (5 * doy + 2) / 153; // [0, 11]
+    let d = doy - (153 * mp + 2) / 5 + 1; // [1, 31]
+    let m = if mp < 10 { mp + 3 } else { mp - 9 }; // [1, 12]
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+/// Output format for the search API's `received_at` field, selected via a
+/// `received_at=<format>` query param. Defaults to the existing raw-epoch-ms
+/// float so old clients see no change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampFormat {
+    #[default]
+    EpochMs,
+    Rfc3339,
+}
+
+impl std::str::FromStr for TimestampFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "epoch_ms" | "epochms" | "ms" => Ok(Self::EpochMs),
+            "rfc3339" | "iso8601" | "iso" => Ok(Self::Rfc3339),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Render a `received_at` epoch-ms value as JSON in the requested format.
+pub fn render_received_at(ms: f64, format: TimestampFormat) -> serde_json::Value {
+    match format {
+        TimestampFormat::EpochMs => serde_json::json!(ms),
+        TimestampFormat::Rfc3339 => serde_json::json!(epoch_ms_to_rfc3339(ms as i64)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrips_known_instant() {
+        let ms = parse_rfc3339_ms("2026-02-17T12:34:56.789Z").unwrap();
+        assert_eq!(epoch_ms_to_rfc3339(ms), "2026-02-17T12:34:56.789Z");
+    }
+
+    #[test]
+    fn accepts_space_separator_and_no_offset() {
+        let ms = parse_rfc3339_ms("2026-02-17 12:34:56.789").unwrap();
+        assert_eq!(epoch_ms_to_rfc3339(ms), "2026-02-17T12:34:56.789Z");
+    }
+
+    #[test]
+    fn truncates_sub_millisecond_precision() {
+        let ms = parse_rfc3339_ms("2026-02-17T12:34:56.789123456Z").unwrap();
+        assert_eq!(epoch_ms_to_rfc3339(ms), "2026-02-17T12:34:56.789Z");
+    }
+
+    #[test]
+    fn pads_short_fractional_digits() {
+        let ms = parse_rfc3339_ms("2026-02-17T12:34:56.7Z").unwrap();
+        assert_eq!(epoch_ms_to_rfc3339(ms), "2026-02-17T12:34:56.700Z");
+    }
+
+    #[test]
+    fn applies_positive_offset_within_same_day() {
+        // 23:30 at +02:00 is 21:30 UTC the same day.
+        let ms = parse_rfc3339_ms("2026-02-17T23:30:00+02:00").unwrap();
+        assert_eq!(epoch_ms_to_rfc3339(ms), "2026-02-17T21:30:00.000Z");
+    }
+
+    #[test]
+    fn applies_negative_offset_and_crosses_day_boundary() {
+        // 01:00 at -05:00 is 06:00 UTC the same day, but 23:00 at -05:00
+        // rolls into the next UTC day.
+        let ms = parse_rfc3339_ms("2026-02-17T23:00:00-05:00").unwrap();
+        assert_eq!(epoch_ms_to_rfc3339(ms), "2026-02-18T04:00:00.000Z");
+    }
+
+    #[test]
+    fn handles_compact_offset_without_colon() {
+        let ms = parse_rfc3339_ms("2026-02-17T23:00:00-0500").unwrap();
+        assert_eq!(epoch_ms_to_rfc3339(ms), "2026-02-18T04:00:00.000Z");
+    }
+
+    #[test]
+    fn leap_day_roundtrips() {
+        let ms = parse_rfc3339_ms("2024-02-29T00:00:00Z").unwrap();
+        assert_eq!(epoch_ms_to_rfc3339(ms), "2024-02-29T00:00:00.000Z");
+    }
+
+    #[test]
+    fn handles_pre_epoch_negative_instant() {
+        let ms = parse_rfc3339_ms("1969-12-31T23:59:58Z").unwrap();
+        assert_eq!(ms, -2000);
+        assert_eq!(epoch_ms_to_rfc3339(ms), "1969-12-31T23:59:58.000Z");
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(parse_rfc3339_ms("not-a-date"), None);
+        assert_eq!(parse_rfc3339_ms("2026-13-01T00:00:00Z"), None);
+    }
+
+    #[test]
+    fn epoch_ms_to_ch_decimal_handles_negative_timestamps() {
+        assert_eq!(epoch_ms_to_ch_decimal(-1), "-1.999");
+        assert_eq!(epoch_ms_to_ch_decimal(1_739_800_496_789), "1739800496.789");
+    }
+}
@@ -1,42 +1,108 @@
 use std::time::Duration;
 use tokio::sync::watch;
 
-use crate::convex::client::ConvexClient;
+use crate::convex::client::{ConvexClient, ConvexError};
+use crate::convex::types::EndpointInfo;
 use crate::redis::RedisState;
+use crate::workers::supervisor::{Worker, WorkerRegistry, WorkerState, spawn_supervised};
 
 const WARM_INTERVAL: Duration = Duration::from_secs(5);
 const ENDPOINT_TTL_REFRESH_THRESHOLD: i64 = 10; // seconds remaining
 const QUOTA_TTL_REFRESH_THRESHOLD: i64 = 5; // seconds remaining
 const MAX_CONCURRENT_WARMS: usize = 8;
 
-/// Spawn a background task that proactively refreshes caches for active slugs.
-pub fn spawn_cache_warmer(
+/// The slice of `RedisState` that `warm_caches` needs — split out so tests
+/// can drive it against an in-memory fake (behind the `mocks` feature, see
+/// `crate::mocks::MockRedisBackend`) instead of a live Redis, same shape as
+/// `workers::flush::FlushRedisBackend`.
+pub(crate) trait CacheWarmerRedisBackend: Send + Sync {
+    async fn active_slugs(&self) -> Vec<String>;
+    async fn endpoint_ttl(&self, slug: &str) -> Option<i64>;
+    async fn quota_ttl(&self, slug: &str) -> Option<i64>;
+}
+
+impl CacheWarmerRedisBackend for RedisState {
+    async fn active_slugs(&self) -> Vec<String> {
+        RedisState::active_slugs(self).await
+    }
+
+    async fn endpoint_ttl(&self, slug: &str) -> Option<i64> {
+        RedisState::endpoint_ttl(self, slug).await
+    }
+
+    async fn quota_ttl(&self, slug: &str) -> Option<i64> {
+        RedisState::quota_ttl(self, slug).await
+    }
+}
+
+/// The slice of `ConvexClient` that `warm_caches` needs — see
+/// `CacheWarmerRedisBackend` for why this is split out.
+pub(crate) trait CacheWarmerConvexBackend: Send + Sync {
+    fn circuit_is_degraded(&self) -> bool;
+    async fn fetch_and_cache_endpoint(&self, slug: &str) -> Result<Option<EndpointInfo>, ConvexError>;
+    async fn fetch_and_cache_quota(&self, slug: &str) -> Result<(), ConvexError>;
+}
+
+impl CacheWarmerConvexBackend for ConvexClient {
+    fn circuit_is_degraded(&self) -> bool {
+        self.circuit().is_degraded()
+    }
+
+    async fn fetch_and_cache_endpoint(&self, slug: &str) -> Result<Option<EndpointInfo>, ConvexError> {
+        ConvexClient::fetch_and_cache_endpoint(self, slug).await
+    }
+
+    async fn fetch_and_cache_quota(&self, slug: &str) -> Result<(), ConvexError> {
+        ConvexClient::fetch_and_cache_quota(self, slug).await
+    }
+}
+
+struct CacheWarmerWorker {
     redis: RedisState,
     convex: ConvexClient,
-    mut shutdown: watch::Receiver<bool>,
-) {
-    tokio::spawn(async move {
-        tracing::info!("cache warmer started");
-
-        loop {
-            if *shutdown.borrow() {
-                tracing::info!("cache warmer shutting down");
-                return;
-            }
+    shutdown: watch::Receiver<bool>,
+}
 
-            warm_caches(&redis, &convex).await;
+impl Worker for CacheWarmerWorker {
+    fn name(&self) -> String {
+        "cache-warmer".to_string()
+    }
 
-            tokio::select! {
-                _ = tokio::time::sleep(WARM_INTERVAL) => {}
-                _ = shutdown.changed() => {}
-            }
+    async fn work(&mut self) -> WorkerState {
+        if *self.shutdown.borrow() {
+            return WorkerState::Done;
         }
-    });
+
+        warm_caches(&self.redis, &self.convex).await;
+        WorkerState::Idle(WARM_INTERVAL)
+    }
+}
+
+/// Spawn a background task that proactively refreshes caches for active slugs.
+pub fn spawn_cache_warmer(
+    registry: &WorkerRegistry,
+    redis: RedisState,
+    convex: ConvexClient,
+    shutdown: watch::Receiver<bool>,
+) {
+    spawn_supervised(
+        registry,
+        move || CacheWarmerWorker {
+            redis: redis.clone(),
+            convex: convex.clone(),
+            shutdown: shutdown.clone(),
+        },
+        shutdown,
+    );
 }
 
-async fn warm_caches(redis: &RedisState, convex: &ConvexClient) {
+async fn warm_caches<R, C>(redis: &R, convex: &C)
+where
+    R: CacheWarmerRedisBackend,
+    C: CacheWarmerConvexBackend + Clone + 'static,
+{
     // Skip warming if Convex is unreachable â€” avoid wasted Redis TTL checks
-    if convex.circuit().is_degraded().await {
+    if convex.circuit_is_degraded() {
         return;
     }
 
@@ -5,6 +5,7 @@ use tokio::sync::watch;
 use crate::clickhouse::client::ClickHouseClient;
 use crate::convex::client::ConvexClient;
 use crate::convex::types::UsersByPlanResponse;
+use crate::workers::supervisor::{Worker, WorkerRegistry, WorkerState, spawn_supervised};
 
 const RETENTION_SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60); // 1 hour
 const FREE_RETENTION_DAYS: u32 = 7;
@@ -51,39 +52,54 @@ impl RequestRetentionDeleter for ClickHouseClient {
     }
 }
 
+struct ClickHouseRetentionWorker {
+    convex: ConvexClient,
+    clickhouse: ClickHouseClient,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl Worker for ClickHouseRetentionWorker {
+    fn name(&self) -> String {
+        "clickhouse-retention".to_string()
+    }
+
+    async fn work(&mut self) -> WorkerState {
+        if *self.shutdown.borrow() {
+            return WorkerState::Done;
+        }
+
+        if let Err(err) = run_free_retention_sweep(&self.convex, &self.clickhouse).await {
+            tracing::warn!(error = %err, "clickhouse retention sweep failed");
+        }
+
+        WorkerState::Idle(RETENTION_SWEEP_INTERVAL)
+    }
+}
+
 /// Spawn a background worker that enforces free-tier ClickHouse retention.
 ///
 /// Convex controls source-of-truth plan state. This worker pages all free users,
 /// then submits ClickHouse mutations to delete rows older than 7 days for those users.
 pub fn spawn_clickhouse_retention_worker(
+    registry: &WorkerRegistry,
     convex: ConvexClient,
     clickhouse: Option<ClickHouseClient>,
-    mut shutdown: watch::Receiver<bool>,
+    shutdown: watch::Receiver<bool>,
 ) {
     let Some(clickhouse) = clickhouse else {
         tracing::info!("clickhouse retention worker disabled: ClickHouse not configured");
         return;
     };
 
-    tokio::spawn(async move {
-        tracing::info!("clickhouse retention worker started");
-
-        loop {
-            if *shutdown.borrow() {
-                tracing::info!("clickhouse retention worker shutting down");
-                return;
-            }
-
-            if let Err(err) = run_free_retention_sweep(&convex, &clickhouse).await {
-                tracing::warn!(error = %err, "clickhouse retention sweep failed");
-            }
-
-            tokio::select! {
-                _ = tokio::time::sleep(RETENTION_SWEEP_INTERVAL) => {}
-                _ = shutdown.changed() => {}
-            }
-        }
-    });
+    spawn_supervised(
+        registry,
+        move || ClickHouseRetentionWorker {
+            convex: convex.clone(),
+            clickhouse: clickhouse.clone(),
+            shutdown: shutdown.clone(),
+        },
+        shutdown,
+    );
 }
 
 async fn run_free_retention_sweep(
@@ -336,7 +352,8 @@ mod tests {
             .route("/", post(mock_clickhouse_delete))
             .with_state(clickhouse_log.clone());
         let clickhouse_base = spawn_http_server(clickhouse_app).await;
-        let clickhouse = ClickHouseClient::new(&clickhouse_base, "ch_user", "ch_pass", "webhooks");
+        let clickhouse =
+            ClickHouseClient::new(&clickhouse_base, "ch_user", "ch_pass", "webhooks", true, 1024);
 
         run_free_retention_sweep(&convex, &clickhouse)
             .await
@@ -1,12 +1,15 @@
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::{Semaphore, watch};
 
 use crate::clickhouse::client::ClickHouseClient;
 use crate::clickhouse::types::ClickHouseRequest;
+use crate::config::{Config, SharedConfig};
 use crate::convex::client::{ConvexClient, ConvexError};
-use crate::convex::types::BufferedRequest;
+use crate::convex::types::{BufferedRequest, CaptureResponse, EndpointInfo};
 use crate::redis::RedisState;
+use crate::redis::lock::LockGuard;
+use crate::workers::supervisor::{Worker, WorkerRegistry, WorkerState, spawn_supervised};
 
 /// How long to sleep when the circuit breaker is open.
 const CIRCUIT_OPEN_BACKOFF: Duration = Duration::from_secs(5);
@@ -14,14 +17,270 @@ const CIRCUIT_OPEN_BACKOFF: Duration = Duration::from_secs(5);
 /// Max concurrent fire-and-forget ClickHouse insert tasks.
 const CH_MAX_CONCURRENT_WRITES: usize = 16;
 
+/// TTL for the per-slug distributed flush lock (`RedisState::try_lock`) —
+/// comfortably longer than a `take_batch` + `capture_batch` + ClickHouse
+/// dual-write round trip should ever take, so the lock doesn't expire out
+/// from under a worker mid-flush and let a second instance double-drain the
+/// same slug. No watchdog renewal: a generous fixed TTL is simpler and a
+/// flush pass that's still running after 30s has bigger problems than an
+/// expired lock.
+const FLUSH_LOCK_TTL_MS: u64 = 30_000;
+
+/// EWMA smoothing factor for `Tranquilizer`'s pass-duration estimate —
+/// weights the latest pass at 25%, so a few consecutive slow (or fast)
+/// passes shift the pacing without one outlier swinging it wildly.
+const TRANQUILIZER_EWMA_ALPHA: f64 = 0.25;
+
+/// Buffer retry queue tunables, read fresh from `Config` on every pass (see
+/// `RedisState::requeue`).
+#[derive(Clone, Copy)]
+pub(crate) struct RetryConfig {
+    max_attempts: u32,
+    base_delay_ms: u64,
+    cap_ms: u64,
+}
+
+/// The slice of `RedisState` that `drain_pass`/`drain_slug`/
+/// `fire_and_forget_clickhouse` need, extracted so tests can exercise the
+/// flush pipeline against an in-memory fake (behind the `mocks` feature,
+/// see `crate::mocks::MockRedisBackend`) instead of a live Redis — same
+/// dependency-injection shape as `clickhouse_retention::PlanUserSource`.
+pub(crate) trait FlushRedisBackend: Send + Sync {
+    /// Opaque handle returned by `try_lock`, passed back to `release_lock`.
+    type Lock: Send;
+
+    async fn active_slugs(&self) -> Vec<String>;
+    async fn take_due_retries(&self, slug: &str, now: i64) -> Vec<BufferedRequest>;
+    async fn take_batch(&self, slug: &str, max: usize) -> Vec<BufferedRequest>;
+    async fn retry_pending_len(&self, slug: &str) -> usize;
+    async fn remove_active(&self, slug: &str);
+    async fn requeue(
+        &self,
+        slug: &str,
+        requests: &[BufferedRequest],
+        last_error: &str,
+        retry_cfg: RetryConfig,
+    );
+    async fn try_lock(&self, key: &str, ttl_ms: u64) -> Option<Self::Lock>;
+    async fn release_lock(&self, guard: Self::Lock);
+    async fn get_endpoint(&self, slug: &str) -> Option<EndpointInfo>;
+}
+
+impl FlushRedisBackend for RedisState {
+    type Lock = LockGuard;
+
+    async fn active_slugs(&self) -> Vec<String> {
+        RedisState::active_slugs(self).await
+    }
+
+    async fn take_due_retries(&self, slug: &str, now: i64) -> Vec<BufferedRequest> {
+        RedisState::take_due_retries(self, slug, now).await
+    }
+
+    async fn take_batch(&self, slug: &str, max: usize) -> Vec<BufferedRequest> {
+        RedisState::take_batch(self, slug, max).await
+    }
+
+    async fn retry_pending_len(&self, slug: &str) -> usize {
+        RedisState::retry_pending_len(self, slug).await
+    }
+
+    async fn remove_active(&self, slug: &str) {
+        RedisState::remove_active(self, slug).await
+    }
+
+    async fn requeue(
+        &self,
+        slug: &str,
+        requests: &[BufferedRequest],
+        last_error: &str,
+        retry_cfg: RetryConfig,
+    ) {
+        RedisState::requeue(
+            self,
+            slug,
+            requests,
+            last_error,
+            retry_cfg.max_attempts,
+            retry_cfg.base_delay_ms,
+            retry_cfg.cap_ms,
+        )
+        .await
+    }
+
+    async fn try_lock(&self, key: &str, ttl_ms: u64) -> Option<LockGuard> {
+        RedisState::try_lock(self, key, ttl_ms).await
+    }
+
+    async fn release_lock(&self, guard: LockGuard) {
+        RedisState::release_lock(self, guard).await
+    }
+
+    async fn get_endpoint(&self, slug: &str) -> Option<EndpointInfo> {
+        RedisState::get_endpoint(self, slug).await
+    }
+}
+
+/// The slice of `ConvexClient` that the flush pipeline needs — see
+/// `FlushRedisBackend` for why this is split out.
+pub(crate) trait FlushConvexBackend: Send + Sync {
+    fn circuit_is_degraded(&self) -> bool;
+    async fn capture_batch(
+        &self,
+        slug: &str,
+        requests: Vec<BufferedRequest>,
+    ) -> Result<CaptureResponse, ConvexError>;
+}
+
+impl FlushConvexBackend for ConvexClient {
+    fn circuit_is_degraded(&self) -> bool {
+        self.circuit().is_degraded()
+    }
+
+    async fn capture_batch(
+        &self,
+        slug: &str,
+        requests: Vec<BufferedRequest>,
+    ) -> Result<CaptureResponse, ConvexError> {
+        ConvexClient::capture_batch(self, slug, requests).await
+    }
+}
+
+/// Adaptive flush pacing, ported from Garage's "tranquilizer": after a pass
+/// that did work, sleep `smoothed_pass_duration * flush_tranquility` so the
+/// worker stays busy a fixed fraction `1/(1+flush_tranquility)` of the time,
+/// instead of hammering Convex as fast as it responds (under load) or
+/// idling at a fixed interval regardless of load (when quiet).
+struct Tranquilizer {
+    ewma_ms: Option<f64>,
+}
+
+impl Tranquilizer {
+    fn new() -> Self {
+        Self { ewma_ms: None }
+    }
+
+    /// Fold in the duration of a pass that did work and return how long to
+    /// sleep before the next one, clamped to `[flush_pacing_min_ms,
+    /// flush_pacing_max_ms]`.
+    fn observe(&mut self, pass_duration: Duration, cfg: &Config) -> Duration {
+        let sample_ms = pass_duration.as_millis() as f64;
+        let ewma_ms = match self.ewma_ms {
+            Some(prev) => prev + TRANQUILIZER_EWMA_ALPHA * (sample_ms - prev),
+            None => sample_ms,
+        };
+        self.ewma_ms = Some(ewma_ms);
+
+        let sleep_ms = (ewma_ms * cfg.flush_tranquility)
+            .clamp(cfg.flush_pacing_min_ms as f64, cfg.flush_pacing_max_ms as f64);
+        Duration::from_millis(sleep_ms as u64)
+    }
+
+    /// Drop the smoothing window — called when the circuit breaker trips, so
+    /// a stale pre-outage EWMA doesn't mis-pace the first passes once Convex
+    /// recovers.
+    fn reset(&mut self) {
+        self.ewma_ms = None;
+    }
+}
+
+/// One flush worker's state between ticks, driven by `workers::supervisor`.
+/// `worker_id`/`worker_count` pin this instance to its strided slice of
+/// slugs (see `drain_pass`) across restarts — a panic-triggered rebuild via
+/// `spawn_flush_workers`'s `make_worker` closure must keep the same ids,
+/// only the `Tranquilizer`'s smoothing window is lost (and rebuilt in a few
+/// ticks, same as after a circuit-breaker trip).
+struct FlushWorker {
+    worker_id: usize,
+    worker_count: usize,
+    redis: RedisState,
+    convex: ConvexClient,
+    clickhouse: Option<Arc<ClickHouseClient>>,
+    config: SharedConfig,
+    ch_semaphore: Arc<Semaphore>,
+    shutdown: watch::Receiver<bool>,
+    tranquilizer: Tranquilizer,
+}
+
+impl Worker for FlushWorker {
+    fn name(&self) -> String {
+        format!("flush-{}", self.worker_id)
+    }
+
+    async fn work(&mut self) -> WorkerState {
+        let cfg = self.config.load();
+        let batch_max_size = cfg.batch_max_size;
+        let retry_cfg = RetryConfig {
+            max_attempts: cfg.buffer_retry_max_attempts,
+            base_delay_ms: cfg.buffer_retry_base_delay_ms,
+            cap_ms: cfg.buffer_retry_cap_ms,
+        };
+
+        if *self.shutdown.borrow() {
+            // Final drain — skip if circuit is open (Convex unreachable,
+            // batches stay in Redis for next startup).
+            if !self.convex.circuit().is_degraded() {
+                drain_pass(
+                    &self.redis,
+                    &self.convex,
+                    self.clickhouse.as_deref(),
+                    &self.ch_semaphore,
+                    batch_max_size,
+                    retry_cfg,
+                    self.worker_id,
+                    self.worker_count,
+                )
+                .await;
+            }
+            return WorkerState::Done;
+        }
+
+        // Don't drain if circuit breaker is open — back off instead.
+        if self.convex.circuit().is_degraded() {
+            tracing::debug!(worker_id = self.worker_id, "circuit breaker open, backing off");
+            // The EWMA built up before the outage no longer reflects how
+            // long a pass against a recovered Convex will take.
+            self.tranquilizer.reset();
+            return WorkerState::Idle(CIRCUIT_OPEN_BACKOFF);
+        }
+
+        let pass_start = Instant::now();
+        let did_work = drain_pass(
+            &self.redis,
+            &self.convex,
+            self.clickhouse.as_deref(),
+            &self.ch_semaphore,
+            batch_max_size,
+            retry_cfg,
+            self.worker_id,
+            self.worker_count,
+        )
+        .await;
+
+        let sleep_for = if did_work {
+            self.tranquilizer.observe(pass_start.elapsed(), &cfg)
+        } else {
+            Duration::from_millis(cfg.flush_interval_ms)
+        };
+        WorkerState::Idle(sleep_for)
+    }
+}
+
 /// Spawn N flush workers that drain Redis request buffers and POST to Convex.
+///
+/// `worker_count` is fixed at spawn time (restarting the pool isn't something
+/// a hot reload can do), but `batch_max_size` and the idle-sleep interval are
+/// read from `config` on every pass so `Config::reload()` takes effect without
+/// restarting the process. Each worker runs under `workers::supervisor`, so a
+/// panic mid-flush restarts that one worker (with a fresh `Tranquilizer`)
+/// instead of silently leaving its slice of slugs undrained forever.
 pub fn spawn_flush_workers(
+    registry: &WorkerRegistry,
     redis: RedisState,
     convex: ConvexClient,
     clickhouse: Option<ClickHouseClient>,
+    config: SharedConfig,
     worker_count: usize,
-    batch_max_size: usize,
-    flush_interval: Duration,
     shutdown: watch::Receiver<bool>,
 ) {
     let clickhouse = clickhouse.map(Arc::new);
@@ -31,79 +290,44 @@ pub fn spawn_flush_workers(
         let redis = redis.clone();
         let convex = convex.clone();
         let clickhouse = clickhouse.clone();
+        let config = config.clone();
         let ch_semaphore = ch_semaphore.clone();
-        let mut shutdown = shutdown.clone();
-
-        tokio::spawn(async move {
-            tracing::info!(worker_id, "flush worker started");
-
-            loop {
-                // Check for shutdown
-                if *shutdown.borrow() {
-                    // Final drain — skip if circuit is open (Convex unreachable,
-                    // batches stay in Redis for next startup)
-                    if !convex.circuit().is_degraded().await {
-                        drain_pass(
-                            &redis,
-                            &convex,
-                            clickhouse.as_deref(),
-                            &ch_semaphore,
-                            batch_max_size,
-                            worker_id,
-                            worker_count,
-                        )
-                        .await;
-                    }
-                    tracing::info!(worker_id, "flush worker shutting down");
-                    return;
-                }
-
-                // Don't drain if circuit breaker is open — back off instead
-                if convex.circuit().is_degraded().await {
-                    tracing::debug!(worker_id, "circuit breaker open, backing off");
-                    tokio::select! {
-                        _ = tokio::time::sleep(CIRCUIT_OPEN_BACKOFF) => {}
-                        _ = shutdown.changed() => {}
-                    }
-                    continue;
-                }
+        let shutdown = shutdown.clone();
 
-                let did_work = drain_pass(
-                    &redis,
-                    &convex,
-                    clickhouse.as_deref(),
-                    &ch_semaphore,
-                    batch_max_size,
-                    worker_id,
-                    worker_count,
-                )
-                .await;
-
-                if !did_work {
-                    tokio::select! {
-                        _ = tokio::time::sleep(flush_interval) => {}
-                        _ = shutdown.changed() => {}
-                    }
-                }
-            }
-        });
+        spawn_supervised(
+            registry,
+            move || FlushWorker {
+                worker_id,
+                worker_count,
+                redis: redis.clone(),
+                convex: convex.clone(),
+                clickhouse: clickhouse.clone(),
+                config: config.clone(),
+                ch_semaphore: ch_semaphore.clone(),
+                shutdown: shutdown.clone(),
+                tranquilizer: Tranquilizer::new(),
+            },
+            shutdown.clone(),
+        );
     }
-
-    // Drop the original receiver so workers can detect shutdown
-    drop(shutdown);
 }
 
 /// Each worker processes a strided subset of shuffled slugs for fair distribution.
 /// Worker 0 processes indices 0, 4, 8, ...; worker 1 processes 1, 5, 9, ...; etc.
-async fn drain_pass(
-    redis: &RedisState,
-    convex: &ConvexClient,
+async fn drain_pass<R, C>(
+    redis: &R,
+    convex: &C,
     clickhouse: Option<&ClickHouseClient>,
     ch_semaphore: &Arc<Semaphore>,
     batch_max_size: usize,
+    retry_cfg: RetryConfig,
     worker_id: usize,
     worker_count: usize,
-) -> bool {
+) -> bool
+where
+    R: FlushRedisBackend + Clone + 'static,
+    C: FlushConvexBackend,
+{
     let mut slugs = redis.active_slugs().await;
     if slugs.is_empty() {
         return false;
@@ -140,79 +364,186 @@ async fn drain_pass(
         let slug = &slugs[idx];
         idx += worker_count;
 
-        let batch = redis.take_batch(slug, batch_max_size).await;
+        // Across-instance mutual exclusion: the stride/shuffle above only
+        // keeps workers *within this process* from double-draining a slug.
+        // Skip the slug this pass if another instance (or another worker
+        // here, on an unlucky stride collision) already holds its lock —
+        // it'll be picked up again next pass.
+        let Some(lock) = redis.try_lock(&format!("lock:flush:{slug}"), FLUSH_LOCK_TTL_MS).await
+        else {
+            continue;
+        };
+
+        if drain_slug(
+            redis,
+            convex,
+            clickhouse,
+            ch_semaphore,
+            batch_max_size,
+            retry_cfg,
+            slug,
+        )
+        .await
+        {
+            did_work = true;
+        }
+
+        redis.release_lock(lock).await;
+    }
+
+    did_work
+}
+
+/// Drain and flush a single slug's buffer, holding the caller's
+/// `lock:flush:{slug}` distributed lock for the duration. Returns whether
+/// there was anything to flush.
+///
+/// Due retries and fresh items are sent to Convex as two separate
+/// `capture_batch` calls rather than one combined batch. `compute_batch_id`
+/// is a Merkle root over exact batch membership, so a combined batch's
+/// `batch_id` would shift every pass as fresh traffic gets folded in
+/// alongside the same retried requests — Convex's `already_committed` dedup
+/// (the whole point of `batch_id`) would then never recognize a resend.
+/// Keeping retries in their own call gives them a stable identity across
+/// attempts regardless of how much fresh traffic also exists.
+async fn drain_slug<R, C>(
+    redis: &R,
+    convex: &C,
+    clickhouse: Option<&ClickHouseClient>,
+    ch_semaphore: &Arc<Semaphore>,
+    batch_max_size: usize,
+    retry_cfg: RetryConfig,
+    slug: &str,
+) -> bool
+where
+    R: FlushRedisBackend + Clone + 'static,
+    C: FlushConvexBackend,
+{
+    // Due retries flush ahead of fresh items so they don't starve behind a
+    // steady stream of new traffic, but as their own batch — see above.
+    let retries = redis.take_due_retries(slug, crate::convex::types::now_ms()).await;
+    let fresh = redis
+        .take_batch(slug, batch_max_size.saturating_sub(retries.len()))
+        .await;
 
-        if batch.is_empty() {
+    if retries.is_empty() && fresh.is_empty() {
+        // A retry could still be waiting out its backoff even though the
+        // live buffer and due-retries are both empty right now — only
+        // drop the slug from the active set once nothing is left pending.
+        if redis.retry_pending_len(slug).await == 0 {
             redis.remove_active(slug).await;
-            continue;
         }
+        return false;
+    }
 
-        did_work = true;
-        let batch_len = batch.len();
-
-        // Clone batch for Convex (capture_batch takes ownership).
-        // On success, move the original batch into the ClickHouse task
-        // to avoid a second clone.
-        let convex_batch = batch.clone();
-        match convex.capture_batch(slug, convex_batch).await {
-            Ok(resp) => {
-                if !resp.error.is_empty() {
-                    tracing::warn!(
-                        slug,
-                        error = resp.error,
-                        "Convex capture_batch returned error"
-                    );
+    if !retries.is_empty() {
+        flush_batch(redis, convex, clickhouse, ch_semaphore, retry_cfg, slug, retries).await;
+    }
+    if !fresh.is_empty() {
+        flush_batch(redis, convex, clickhouse, ch_semaphore, retry_cfg, slug, fresh).await;
+    }
+
+    true
+}
+
+/// Send one batch to Convex and resolve the outcome — requeue, dead-letter,
+/// or fire-and-forget the ClickHouse dual-write. Split out of `drain_slug`
+/// so due retries and fresh items can each get their own `capture_batch`
+/// call (and their own independently stable `batch_id`).
+async fn flush_batch<R, C>(
+    redis: &R,
+    convex: &C,
+    clickhouse: Option<&ClickHouseClient>,
+    ch_semaphore: &Arc<Semaphore>,
+    retry_cfg: RetryConfig,
+    slug: &str,
+    batch: Vec<BufferedRequest>,
+) where
+    R: FlushRedisBackend + Clone + 'static,
+    C: FlushConvexBackend,
+{
+    let batch_len = batch.len();
+
+    // Clone batch for Convex (capture_batch takes ownership).
+    // On success, move the original batch into the ClickHouse task
+    // to avoid a second clone.
+    let convex_batch = batch.clone();
+    let flush_start = std::time::Instant::now();
+    let capture_result = convex.capture_batch(slug, convex_batch).await;
+    crate::metrics::record_flush_batch_duration(flush_start.elapsed());
+    match capture_result {
+        Ok(resp) => {
+            if !resp.error.is_empty() {
+                tracing::warn!(
+                    slug,
+                    error = resp.error,
+                    "Convex capture_batch returned error"
+                );
+            } else {
+                if resp.already_committed {
+                    // Convex recognized this batch's `batch_id` from an
+                    // earlier attempt whose response we never saw, and
+                    // skipped re-inserting it. The ClickHouse dual-write
+                    // below still needs to happen though — our side never
+                    // got far enough to attempt it on that earlier try.
+                    tracing::debug!(slug, "batch already committed by Convex, resolving retry");
                 } else {
                     tracing::debug!(slug, inserted = resp.inserted, "flushed batch to Convex");
-
-                    // Fire-and-forget ClickHouse dual-write after successful Convex flush.
-                    // Semaphore limits concurrent writes to prevent unbounded task spawning.
-                    if let Some(ch) = clickhouse {
-                        fire_and_forget_clickhouse(
-                            ch.clone(),
-                            redis.clone(),
-                            slug.clone(),
-                            batch, // move, not clone
-                            ch_semaphore.clone(),
-                        );
-                    }
                 }
-            }
-            Err(ref e) => {
-                // Only re-enqueue when CERTAIN Convex did not commit:
-                // - CircuitOpen: request was never sent
-                //
-                // All other errors (ServerError, Network, ClientError) may
-                // mean Convex committed but we didn't get the response.
-                // Drop the batch to avoid duplicates (at-most-once delivery).
-                if matches!(e, ConvexError::CircuitOpen) {
-                    tracing::warn!(slug, count = batch_len, "circuit open, re-enqueuing batch");
-                    redis.requeue(slug, &batch).await;
-                } else {
-                    tracing::error!(
-                        slug,
-                        error = %e,
-                        count = batch_len,
-                        "batch capture failed, dropping batch (at-most-once)"
+
+                // Fire-and-forget ClickHouse dual-write after successful Convex flush.
+                // Semaphore limits concurrent writes to prevent unbounded task spawning.
+                if let Some(ch) = clickhouse {
+                    fire_and_forget_clickhouse(
+                        ch.clone(),
+                        redis.clone(),
+                        slug.to_string(),
+                        batch, // move, not clone
+                        ch_semaphore.clone(),
                     );
                 }
             }
         }
+        Err(ref e) => {
+            // Re-enqueue on anything that doesn't rule out a successful
+            // commit: CircuitOpen (never sent), ServerError/Network (sent,
+            // but the response was lost or Convex errored after persisting).
+            // `BatchPayload::batch_id` makes the resend safe — Convex
+            // recognizes the repeat and no-ops instead of double-inserting.
+            //
+            // ClientError/ParseError mean Convex actually rejected or
+            // couldn't parse what we sent, so retrying the same bytes would
+            // just fail the same way again — drop those.
+            if matches!(
+                e,
+                ConvexError::CircuitOpen | ConvexError::ServerError(_, _) | ConvexError::Network(_)
+            ) {
+                tracing::warn!(slug, count = batch_len, error = %e, "re-enqueuing batch");
+                redis.requeue(slug, &batch, &e.to_string(), retry_cfg).await;
+            } else {
+                tracing::error!(
+                    slug,
+                    error = %e,
+                    count = batch_len,
+                    "batch capture rejected, dropping batch"
+                );
+            }
+        }
     }
-
-    did_work
 }
 
 /// Spawn a background task to write a batch to ClickHouse (fire-and-forget).
 /// Looks up endpoint info from Redis cache for metadata enrichment.
 /// Bounded by semaphore to prevent unbounded task accumulation.
-fn fire_and_forget_clickhouse(
+fn fire_and_forget_clickhouse<R>(
     ch: ClickHouseClient,
-    redis: RedisState,
+    redis: R,
     slug: String,
     batch: Vec<BufferedRequest>,
     semaphore: Arc<Semaphore>,
-) {
+) where
+    R: FlushRedisBackend + Clone + 'static,
+{
     tokio::spawn(async move {
         // Acquire semaphore permit — drops batch if ClickHouse is backed up
         let _permit = match semaphore.try_acquire() {
@@ -252,3 +583,105 @@ fn fire_and_forget_clickhouse(
         }
     });
 }
+
+#[cfg(all(test, feature = "mocks"))]
+mod tests {
+    use super::{RetryConfig, drain_pass};
+    use crate::mocks::{ConvexFault, MockConvexBackend, MockRedisBackend};
+    use std::sync::Arc;
+    use tokio::sync::Semaphore;
+
+    fn sample_request() -> crate::convex::types::BufferedRequest {
+        crate::convex::types::BufferedRequest {
+            method: "POST".to_string(),
+            path: "/".to_string(),
+            headers: Default::default(),
+            body: "{}".to_string(),
+            query_params: Default::default(),
+            ip: "127.0.0.1".to_string(),
+            received_at: 0,
+            attempts: 0,
+        }
+    }
+
+    fn retry_cfg() -> RetryConfig {
+        RetryConfig {
+            max_attempts: 5,
+            base_delay_ms: 100,
+            cap_ms: 1_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn requeues_batch_on_server_error() {
+        let redis = MockRedisBackend::new();
+        let convex = MockConvexBackend::new();
+        redis.set_active_slugs(vec!["acme".to_string()]);
+        redis.queue_batch("acme", vec![sample_request()]);
+        convex.queue_capture_fault("acme", ConvexFault::ServerError);
+
+        let did_work = drain_pass(
+            &redis,
+            &convex,
+            None,
+            &Arc::new(Semaphore::new(1)),
+            10,
+            retry_cfg(),
+            0,
+            1,
+        )
+        .await;
+
+        assert!(did_work);
+        let requeued = redis.requeued_batches();
+        assert_eq!(requeued.len(), 1);
+        assert_eq!(requeued[0].0, "acme");
+        assert_eq!(requeued[0].1.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn drops_batch_on_client_error_without_requeuing() {
+        let redis = MockRedisBackend::new();
+        let convex = MockConvexBackend::new();
+        redis.set_active_slugs(vec!["acme".to_string()]);
+        redis.queue_batch("acme", vec![sample_request()]);
+        convex.queue_capture_fault("acme", ConvexFault::ClientError);
+
+        let did_work = drain_pass(
+            &redis,
+            &convex,
+            None,
+            &Arc::new(Semaphore::new(1)),
+            10,
+            retry_cfg(),
+            0,
+            1,
+        )
+        .await;
+
+        assert!(did_work);
+        assert!(redis.requeued_batches().is_empty());
+    }
+
+    #[tokio::test]
+    async fn drops_active_slug_once_buffer_and_retries_are_empty() {
+        let redis = MockRedisBackend::new();
+        let convex = MockConvexBackend::new();
+        redis.set_active_slugs(vec!["idle".to_string()]);
+
+        let did_work = drain_pass(
+            &redis,
+            &convex,
+            None,
+            &Arc::new(Semaphore::new(1)),
+            10,
+            retry_cfg(),
+            0,
+            1,
+        )
+        .await;
+
+        assert!(!did_work);
+        assert_eq!(redis.removed_active_slugs(), vec!["idle".to_string()]);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod cache_warmer;
+pub mod clickhouse_retention;
+pub mod flush;
+pub mod spill_reconciler;
+pub mod supervisor;
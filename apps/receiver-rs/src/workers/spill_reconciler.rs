@@ -0,0 +1,89 @@
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::config::SharedConfig;
+use crate::redis::RedisState;
+use crate::workers::supervisor::{Worker, WorkerRegistry, WorkerState, spawn_supervised};
+
+/// How often to check whether Redis has come back, once the spill store is
+/// non-empty.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Requests drained from disk per `SpillStore::drain_oldest` call — bounds how
+/// much work one reconcile pass does before yielding back to the poll loop.
+const DRAIN_BATCH_SIZE: usize = 200;
+
+struct SpillReconcilerWorker {
+    redis: RedisState,
+    config: SharedConfig,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl Worker for SpillReconcilerWorker {
+    fn name(&self) -> String {
+        "spill-reconciler".to_string()
+    }
+
+    async fn work(&mut self) -> WorkerState {
+        if *self.shutdown.borrow() {
+            return WorkerState::Done;
+        }
+
+        if !self.redis.spill.is_empty() && self.redis.ping().await {
+            reconcile(&self.redis, &self.config).await;
+        }
+
+        WorkerState::Idle(POLL_INTERVAL)
+    }
+}
+
+/// Spawn a background task that drains `crate::spill::SpillStore` back into
+/// Redis once `RedisState::ping` succeeds again, via `RedisState::requeue`
+/// into each slug's `buf:retry:{slug}` queue (so a spilled request that keeps failing to flush
+/// still eventually dead-letters instead of looping forever), in FIFO order,
+/// deleting drained keys as it goes.
+pub fn spawn_spill_reconciler(
+    registry: &WorkerRegistry,
+    redis: RedisState,
+    config: SharedConfig,
+    shutdown: watch::Receiver<bool>,
+) {
+    spawn_supervised(
+        registry,
+        move || SpillReconcilerWorker {
+            redis: redis.clone(),
+            config: config.clone(),
+            shutdown: shutdown.clone(),
+        },
+        shutdown,
+    );
+}
+
+/// Drain the spill store in `DRAIN_BATCH_SIZE` chunks until it's empty.
+async fn reconcile(redis: &RedisState, config: &SharedConfig) {
+    loop {
+        let groups = redis.spill.drain_oldest(DRAIN_BATCH_SIZE);
+        if groups.is_empty() {
+            return;
+        }
+
+        let cfg = config.load();
+        for (slug, requests) in groups {
+            tracing::info!(
+                slug,
+                count = requests.len(),
+                "reconciling spilled requests back into Redis"
+            );
+            redis
+                .requeue(
+                    &slug,
+                    &requests,
+                    "recovered from disk spill after a Redis outage",
+                    cfg.buffer_retry_max_attempts,
+                    cfg.buffer_retry_base_delay_ms,
+                    cfg.buffer_retry_cap_ms,
+                )
+                .await;
+        }
+    }
+}
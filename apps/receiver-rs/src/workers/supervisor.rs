@@ -0,0 +1,174 @@
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use crate::convex::types::now_ms;
+
+/// Minimum backoff before retrying a worker whose `work()` panicked.
+const INITIAL_RESTART_BACKOFF: Duration = Duration::from_millis(500);
+/// Cap on the panic-restart backoff, so a worker stuck panicking in a tight
+/// loop settles at a slow, steady retry rate instead of spinning.
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(30);
+
+/// What a `Worker` wants the supervisor to do after one `work()` call.
+pub enum WorkerState {
+    /// There's more to do right now — call `work()` again immediately.
+    Busy,
+    /// Nothing to do; sleep for the given duration (or until shutdown)
+    /// before the next call.
+    Idle(Duration),
+    /// The worker is finished for good (e.g. it observed shutdown) — stop
+    /// calling it and let the supervisor task exit.
+    Done,
+}
+
+/// A background task driven by `spawn_supervised`. Modeled on Garage's
+/// `background/worker.rs`: implementors hold whatever state they need
+/// between ticks and react to shutdown themselves, so the supervisor stays
+/// generic over pacing, panic recovery, and health reporting.
+pub trait Worker: Send + 'static {
+    /// Human-readable name for tracing and the `/health` worker snapshot.
+    /// Not `&'static str` because multiple instances of the same worker type
+    /// (e.g. flush workers 0..N) want distinct names.
+    fn name(&self) -> String;
+
+    /// Do one unit of work and report what the supervisor should do next.
+    /// Implementors that need to stop on shutdown must check their own
+    /// `watch::Receiver<bool>` here and return `WorkerState::Done` — the
+    /// supervisor itself has no opinion on when a worker should stop.
+    async fn work(&mut self) -> WorkerState;
+}
+
+/// Liveness/restart counters for one supervised worker, queryable for a
+/// `/health`-style endpoint (see `handlers::health`).
+pub struct WorkerHealth {
+    name: String,
+    last_tick_ms: AtomicI64,
+    restarts: AtomicU64,
+    running: AtomicBool,
+}
+
+impl WorkerHealth {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            last_tick_ms: AtomicI64::new(now_ms()),
+            restarts: AtomicU64::new(0),
+            running: AtomicBool::new(true),
+        }
+    }
+
+    fn record_tick(&self) {
+        self.last_tick_ms.store(now_ms(), Ordering::Relaxed);
+    }
+
+    fn record_restart(&self) {
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn mark_stopped(&self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn last_tick_ms(&self) -> i64 {
+        self.last_tick_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn restarts(&self) -> u64 {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+}
+
+/// Registry of every supervised worker's `WorkerHealth`, shared via
+/// `AppState` so `handlers::health` can snapshot them.
+#[derive(Clone, Default)]
+pub struct WorkerRegistry(Arc<Mutex<Vec<Arc<WorkerHealth>>>>);
+
+impl WorkerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn register(&self, health: Arc<WorkerHealth>) {
+        self.0.lock().unwrap().push(health);
+    }
+
+    pub fn snapshot(&self) -> Vec<Arc<WorkerHealth>> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Spawn `make_worker()` under supervision. Drives the resulting worker's
+/// `work()` in a loop — sleeping between `Idle` ticks (woken early by
+/// shutdown), stopping cleanly on `Done` — and, if a tick panics, rebuilds a
+/// fresh worker via `make_worker` and retries with exponential backoff
+/// instead of leaving that background task dead for the rest of the
+/// process's life. Registers a `WorkerHealth` in `registry` for `/health`.
+pub fn spawn_supervised<W, F>(registry: &WorkerRegistry, make_worker: F, mut shutdown: watch::Receiver<bool>)
+where
+    W: Worker,
+    F: Fn() -> W + Send + Sync + 'static,
+{
+    let mut worker = make_worker();
+    let health = Arc::new(WorkerHealth::new(worker.name()));
+    registry.register(health.clone());
+
+    tokio::spawn(async move {
+        let mut backoff = INITIAL_RESTART_BACKOFF;
+
+        loop {
+            let join = tokio::spawn(async move {
+                let state = worker.work().await;
+                (worker, state)
+            });
+
+            match join.await {
+                Ok((returned_worker, state)) => {
+                    worker = returned_worker;
+                    health.record_tick();
+                    backoff = INITIAL_RESTART_BACKOFF;
+
+                    match state {
+                        WorkerState::Done => {
+                            health.mark_stopped();
+                            tracing::info!(worker = health.name(), "worker stopped");
+                            return;
+                        }
+                        WorkerState::Busy => continue,
+                        WorkerState::Idle(duration) => {
+                            tokio::select! {
+                                _ = tokio::time::sleep(duration) => {}
+                                _ = shutdown.changed() => {}
+                            }
+                        }
+                    }
+                }
+                Err(join_err) => {
+                    health.record_restart();
+                    tracing::error!(
+                        worker = health.name(),
+                        error = %join_err,
+                        backoff_ms = backoff.as_millis() as u64,
+                        "worker panicked, restarting with a fresh instance"
+                    );
+                    worker = make_worker();
+                    tokio::select! {
+                        _ = tokio::time::sleep(backoff) => {}
+                        _ = shutdown.changed() => {}
+                    }
+                    backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+                }
+            }
+        }
+    });
+}